@@ -0,0 +1,3 @@
+fn main() {
+    slint_build::compile("src/ui/app-window.slint").unwrap();
+}
@@ -1 +1,2 @@
-pub mod animation;
\ No newline at end of file
+pub mod animation;
+pub mod plan;
@@ -0,0 +1,124 @@
+use chrono::NaiveTime;
+use crate::course::campus::CampusMap;
+use crate::course::catalog::FreshnessPolicy;
+use crate::course::constraint::{SeatAvailability, WalkingTime};
+use crate::course::course_manager::EnrollmentMode;
+use crate::course::course_scheduler::Scheduler;
+use crate::course::objective::{AvoidEarlyMornings, MaximizeFreeDays, MinimizeGapTime, MinimizeWalking, PreferCompactDays, ProtectLunchBreak};
+use crate::error::CusisResult;
+use crate::source::SourceChoice;
+use crate::webdriver::util::prompt_for_courses;
+use std::sync::Arc;
+
+pub const USAGE: &str = "usage: cuhk-scheduler --plan [--term TERM] [--import FILE | --pages DIR] [COURSE...]";
+
+// Plans from the course catalog, an import file or saved pages without opening the browser
+#[derive(Debug, PartialEq)]
+pub struct PlanArgs {
+    pub term: String,
+    pub choice: SourceChoice,
+    pub courses: Vec<String>
+}
+
+impl PlanArgs {
+    // None unless `--plan` is among `args`, the source named in the environment is used when none is given
+    pub fn parse(args: &[String]) -> Option<Result<PlanArgs, String>> {
+        if !args.iter().any(|arg| arg == "--plan") {
+            return None;
+        }
+        Some(PlanArgs::parse_plan(args))
+    }
+
+    fn parse_plan(args: &[String]) -> Result<PlanArgs, String> {
+        let mut term = String::new();
+        let mut choice = None;
+        let mut courses = vec![];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--plan" | "--show-browser" => {}
+                "--term" => term = value()?,
+                "--import" => choice = Some(SourceChoice::Import(value()?)),
+                "--pages" => choice = Some(SourceChoice::SavedPages(value()?)),
+                option if option.starts_with("--") => return Err(format!("Unknown option {}", option)),
+                course => courses.push(course.to_uppercase())
+            }
+        }
+        let choice = choice.or_else(SourceChoice::from_env).unwrap_or(SourceChoice::Catalog);
+        // the catalog keeps its courses per term, a file or saved pages hold a single term
+        if choice == SourceChoice::Catalog && term.is_empty() {
+            return Err("--term is needed to plan from the course catalog".to_string());
+        }
+        Ok(PlanArgs { term, choice, courses })
+    }
+}
+
+// prints the best schedule with its score and why it ranks above the runner-up, or what keeps the courses
+// from fitting together, the preferences are the registration page's defaults
+pub async fn run(args: PlanArgs) -> CusisResult<()> {
+    // the courses are asked for when none were given on the command line
+    let courses = if args.courses.is_empty() {
+        prompt_for_courses().await.split_whitespace().map(str::to_uppercase).collect()
+    } else {
+        args.courses
+    };
+    let source = args.choice.build(FreshnessPolicy::from_env(false), None);
+    let course_collection = source.fetch(&args.term, &courses).await?;
+
+    let campus = Arc::new(CampusMap::load());
+    let mut scheduler = Scheduler::new();
+    scheduler.add_constraint(WalkingTime(campus.clone()));
+    scheduler.add_constraint(SeatAvailability(EnrollmentMode::OpenOnly));
+    scheduler.add_objective(MinimizeGapTime, 1);
+    scheduler.add_objective(MaximizeFreeDays, 100);
+    scheduler.add_objective(AvoidEarlyMornings(NaiveTime::from_hms_opt(10, 30, 0).unwrap()), 1);
+    scheduler.add_objective(PreferCompactDays, 20);
+    scheduler.add_objective(MinimizeWalking(campus), 1);
+    scheduler.add_objective(
+        ProtectLunchBreak {
+            start: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+            minimum_minutes: 45
+        },
+        1
+    );
+    scheduler.generate_schedule(&course_collection);
+
+    println!("{} schedules from {}", scheduler.get_candidate_count(), source.name());
+    match scheduler.get_schedule_with_best_fitness_score() {
+        Some(codes) => {
+            println!("Class numbers to enroll:");
+            for (course, classes) in codes {
+                println!("  {}: {}", course, classes.join(" "));
+            }
+        }
+        None => match scheduler.diagnose_infeasibility(&course_collection) {
+            Some(report) => println!("{}", report.summary()),
+            None => println!("Sorry no possible schedule can be generated!")
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_plan_args_need_the_plan_flag() {
+        assert!(PlanArgs::parse(&args("--show-browser")).is_none());
+        let parsed = PlanArgs::parse(&args("--plan --import courses.csv csci3100 ELTU3502")).unwrap();
+        assert_eq!(parsed, Ok(PlanArgs {
+            term: String::new(),
+            choice: SourceChoice::Import("courses.csv".to_string()),
+            courses: vec!["CSCI3100".to_string(), "ELTU3502".to_string()]
+        }));
+        assert!(PlanArgs::parse(&args("--plan --import")).unwrap().is_err());
+        assert!(PlanArgs::parse(&args("--plan --pages saved --verbose CSCI3100")).unwrap().is_err());
+    }
+}
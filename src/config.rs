@@ -0,0 +1,99 @@
+use std::env;
+use std::path::PathBuf;
+use crate::webdriver::browser::BrowserBackend;
+
+// Where the app finds CUSIS. Every URL can be overridden through the environment, and setting
+// $CUSIS_MOCK_ADDR (e.g. "127.0.0.1:8089") starts the bundled mock portal and points every URL at it.
+const MOCK_ADDR_ENV: &str = "CUSIS_MOCK_ADDR";
+const LOGIN_LINK_ENV: &str = "CUSIS_LINK";
+const COURSE_SEARCH_LINK_ENV: &str = "CUSIS_COURSE_SEARCH_LINK";
+const SHOPPING_CART_LINK_ENV: &str = "CUSIS_SHOPPING_CART_LINK";
+const CLASS_SCHEDULE_LINK_ENV: &str = "CUSIS_CLASS_SCHEDULE_LINK";
+
+pub fn mock_server_addr() -> Option<String> {
+    env::var(MOCK_ADDR_ENV).ok().filter(|addr| !addr.trim().is_empty())
+}
+
+fn configured_link(env_name: &str, mock_path: &str, default: &str) -> String {
+    if let Ok(link) = env::var(env_name) {
+        return link;
+    }
+    match mock_server_addr() {
+        Some(addr) => format!("http://{}{}", addr, mock_path),
+        None => default.to_string()
+    }
+}
+
+pub fn cusis_link() -> String {
+    configured_link(LOGIN_LINK_ENV, "/login", crate::CUSIS_LINK)
+}
+
+pub fn cusis_course_search_link() -> String {
+    configured_link(COURSE_SEARCH_LINK_ENV, "/class-search", crate::CUSIS_COURSE_SEARCH_LINK)
+}
+
+pub fn cusis_shopping_cart_link() -> String {
+    configured_link(SHOPPING_CART_LINK_ENV, "/cart", crate::CUSIS_SHOPPING_CART_LINK)
+}
+
+pub fn cusis_class_schedule_link() -> String {
+    configured_link(CLASS_SCHEDULE_LINK_ENV, "/my-schedule", crate::CUSIS_CLASS_SCHEDULE_LINK)
+}
+
+// a JSON or CSV file of course options to plan with instead of CUSIS
+const IMPORT_FILE_ENV: &str = "CUSIS_IMPORT_FILE";
+// a directory of saved "Course Information" pages named after their course, e.g. CSCI3100.html
+const FIXTURE_DIR_ENV: &str = "CUSIS_FIXTURE_DIR";
+
+pub fn course_import_file() -> Option<String> {
+    env::var(IMPORT_FILE_ENV).ok().filter(|path| !path.trim().is_empty())
+}
+
+pub fn course_fixture_dir() -> Option<String> {
+    env::var(FIXTURE_DIR_ENV).ok().filter(|path| !path.trim().is_empty())
+}
+
+// "firefox" or "chromium" ("chrome" works too), unset picks whichever browser is installed
+const BROWSER_ENV: &str = "CUSIS_BROWSER";
+// "1" or "true" (or running with --show-browser) shows the browser while it is driven, it is hidden otherwise
+const SHOW_BROWSER_ENV: &str = "CUSIS_SHOW_BROWSER";
+// failure screenshots are saved here, ./screenshots by default
+const SCREENSHOT_DIR_ENV: &str = "CUSIS_SCREENSHOT_DIR";
+
+pub fn browser_backend() -> BrowserBackend {
+    match env::var(BROWSER_ENV) {
+        Ok(name) => BrowserBackend::parse(&name).unwrap_or_else(|| {
+            eprintln!("Unknown browser \"{}\", using {}", name, BrowserBackend::detect().name());
+            BrowserBackend::detect()
+        }),
+        Err(_) => BrowserBackend::detect()
+    }
+}
+
+// headless unless the browser is asked for, a Linux box without a display server cannot open a window either way
+pub fn headless() -> bool {
+    let show_browser = env::args().any(|arg| arg == "--show-browser")
+        || env::var(SHOW_BROWSER_ENV).is_ok_and(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"));
+    if show_browser && cfg!(target_os = "linux") && env::var_os("DISPLAY").is_none() && env::var_os("WAYLAND_DISPLAY").is_none() {
+        eprintln!("No display available, the browser stays hidden");
+        return true;
+    }
+    !show_browser
+}
+
+pub fn screenshot_dir() -> PathBuf {
+    env::var_os(SCREENSHOT_DIR_ENV).filter(|dir| !dir.is_empty()).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("screenshots"))
+}
+
+// the encrypted cookies of the last session, see webdriver::cookie_store, ./cusis_session.bin by default
+const SESSION_FILE_ENV: &str = "CUSIS_SESSION_FILE";
+// without a passphrase the session is not kept across runs
+const SESSION_PASSPHRASE_ENV: &str = "CUSIS_SESSION_PASSPHRASE";
+
+pub fn session_file() -> PathBuf {
+    env::var_os(SESSION_FILE_ENV).filter(|path| !path.is_empty()).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("cusis_session.bin"))
+}
+
+pub fn session_passphrase() -> Option<String> {
+    env::var(SESSION_PASSPHRASE_ENV).ok().filter(|passphrase| !passphrase.is_empty())
+}
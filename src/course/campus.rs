@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::{env, fs};
+use super::meeting::Meeting;
+
+// the table shipped with the binary, see campus_distances.csv for the format
+const BUNDLED_CAMPUS_DATA: &str = include_str!("campus_distances.csv");
+// a local copy of the table in the working directory takes precedence over the bundled one
+const LOCAL_CAMPUS_DATA: &str = "campus_distances.csv";
+// points at a campus table anywhere on disk, takes precedence over the local copy
+const CAMPUS_DATA_ENV: &str = "CUHK_CAMPUS_DATA";
+
+// Which zone every building belongs to and how long it takes to walk between zones
+#[derive(Debug, Clone, Default)]
+pub struct CampusMap {
+    // (lowercase building name or abbreviation, zone)
+    buildings: Vec<(String, String)>,
+    walking_minutes: HashMap<(String, String), i64>
+}
+
+impl CampusMap {
+    pub fn parse(data: &str) -> Result<CampusMap, String> {
+        let mut campus = CampusMap::default();
+        for (line_number, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            match fields.as_slice() {
+                ["building", building, zone] => {
+                    campus.buildings.push((building.to_lowercase(), zone.to_string()));
+                }
+                ["walk", from, to, minutes] => {
+                    let minutes = minutes.parse::<i64>()
+                        .map_err(|_| format!("line {}: invalid walking minutes \"{}\"", line_number + 1, minutes))?;
+                    campus.walking_minutes.insert((from.to_string(), to.to_string()), minutes);
+                    campus.walking_minutes.insert((to.to_string(), from.to_string()), minutes);
+                }
+                _ => return Err(format!("line {}: cannot parse \"{}\"", line_number + 1, line))
+            }
+        }
+        Ok(campus)
+    }
+
+    // loads the table from $CUHK_CAMPUS_DATA, then ./campus_distances.csv, then the bundled copy
+    pub fn load() -> CampusMap {
+        let override_path = env::var(CAMPUS_DATA_ENV).unwrap_or_else(|_| LOCAL_CAMPUS_DATA.to_string());
+        if let Ok(data) = fs::read_to_string(&override_path) {
+            match CampusMap::parse(&data) {
+                Ok(campus) => return campus,
+                Err(e) => eprintln!("Ignoring campus table {}: {}", override_path, e)
+            }
+        }
+        CampusMap::parse(BUNDLED_CAMPUS_DATA).expect("bundled campus table is malformed")
+    }
+
+    // the zone of a CUSIS venue such as "Lady Shaw Bldg LT1" or "LSB LT1", the longest matching building name wins
+    pub fn zone_of(&self, room: &str) -> Option<&str> {
+        let room = room.trim().to_lowercase();
+        self.buildings.iter()
+            .filter(|(building, _)| {
+                room.strip_prefix(building.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+            })
+            .max_by_key(|(building, _)| building.len())
+            .map(|(_, zone)| zone.as_str())
+    }
+
+    // walking minutes between the venues of two meetings, 0 when either venue is unknown or both are in the same zone
+    pub fn walking_minutes(&self, from: &Meeting, to: &Meeting) -> i64 {
+        match (self.zone_of(&from.room), self.zone_of(&to.room)) {
+            (Some(from_zone), Some(to_zone)) if from_zone != to_zone => {
+                self.walking_minutes.get(&(from_zone.to_string(), to_zone.to_string())).copied().unwrap_or(0)
+            }
+            _ => 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+    use crate::course::meeting::Weekday;
+
+    fn meeting_at(room: &str, start: (u32, u32), end: (u32, u32)) -> Meeting {
+        let mut meeting = Meeting::new(Weekday::Monday, NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(), NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap()).unwrap();
+        meeting.room = room.to_string();
+        meeting
+    }
+
+    #[test]
+    fn test_bundled_campus_table() {
+        let campus = CampusMap::parse(BUNDLED_CAMPUS_DATA).unwrap();
+        assert_eq!(campus.zone_of("Lady Shaw Bldg LT1"), Some("Central Campus"));
+        assert_eq!(campus.zone_of("LSB C1"), Some("Central Campus"));
+        assert_eq!(campus.zone_of("LSBX 101"), None);
+        let central = meeting_at("Lady Shaw Bldg LT1", (10, 30), (12, 15));
+        let united = meeting_at("T.C. Cheng Bldg 201", (12, 30), (14, 15));
+        assert_eq!(campus.walking_minutes(&central, &united), 20);
+        assert_eq!(campus.walking_minutes(&united, &central), 20);
+        assert_eq!(campus.walking_minutes(&central, &meeting_at("MMW 703", (12, 30), (13, 15))), 0);
+        assert_eq!(campus.walking_minutes(&central, &meeting_at("TBA", (12, 30), (13, 15))), 0);
+        assert!(CampusMap::parse("walk,Central Campus,United College,far").is_err());
+    }
+}
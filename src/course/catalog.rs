@@ -0,0 +1,138 @@
+use std::env;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use super::course_manager::Course;
+
+// where the catalog lives unless $CUSIS_CATALOG_PATH points elsewhere
+const DEFAULT_CATALOG_PATH: &str = "course_catalog.sqlite3";
+const CATALOG_PATH_ENV: &str = "CUSIS_CATALOG_PATH";
+// hours a cached course stays fresh unless $CUSIS_CATALOG_MAX_AGE_HOURS says otherwise
+const DEFAULT_MAX_AGE_HOURS: i64 = 24;
+const MAX_AGE_ENV: &str = "CUSIS_CATALOG_MAX_AGE_HOURS";
+
+// The options of one course as they were scraped at `fetched_at`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedCourse {
+    pub options: Vec<Course>,
+    pub fetched_at: DateTime<Utc>
+}
+
+// Decides whether a cached course can be used instead of scraping it again
+#[derive(Debug, Clone, Copy)]
+pub struct FreshnessPolicy {
+    pub max_age: Duration,
+    // set when the user asked for fresh data, every cached entry is then stale
+    pub force_refresh: bool
+}
+
+impl FreshnessPolicy {
+    pub fn from_env(force_refresh: bool) -> FreshnessPolicy {
+        let hours = env::var(MAX_AGE_ENV).ok()
+            .and_then(|hours| hours.trim().parse::<i64>().ok())
+            .unwrap_or(DEFAULT_MAX_AGE_HOURS);
+        FreshnessPolicy {
+            max_age: Duration::hours(hours),
+            force_refresh
+        }
+    }
+
+    pub fn is_fresh(&self, cached: &CachedCourse, now: DateTime<Utc>) -> bool {
+        !self.force_refresh && now - cached.fetched_at <= self.max_age
+    }
+}
+
+// Local store of scraped course data keyed by term, so schedules can be built without the portal
+pub struct CourseCatalog {
+    connection: Connection
+}
+
+impl CourseCatalog {
+    pub fn open(path: &str) -> rusqlite::Result<CourseCatalog> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS courses (
+                term TEXT NOT NULL,
+                course_name TEXT NOT NULL,
+                fetched_at TEXT NOT NULL,
+                options TEXT NOT NULL,
+                PRIMARY KEY (term, course_name)
+            );"
+        )?;
+        Ok(CourseCatalog { connection })
+    }
+
+    pub fn open_default() -> rusqlite::Result<CourseCatalog> {
+        let path = env::var(CATALOG_PATH_ENV).unwrap_or_else(|_| DEFAULT_CATALOG_PATH.to_string());
+        CourseCatalog::open(&path)
+    }
+
+    pub fn save(&self, term: &str, course_name: &str, options: &[Course], fetched_at: DateTime<Utc>) -> rusqlite::Result<()> {
+        let options = serde_json::to_string(options)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.connection.execute(
+            "INSERT OR REPLACE INTO courses (term, course_name, fetched_at, options) VALUES (?1, ?2, ?3, ?4)",
+            params![term, course_name, fetched_at.to_rfc3339(), options]
+        )?;
+        Ok(())
+    }
+
+    // None when the course was never cached for this term or the stored row cannot be read back
+    pub fn load(&self, term: &str, course_name: &str) -> rusqlite::Result<Option<CachedCourse>> {
+        let row: Option<(String, String)> = self.connection.query_row(
+            "SELECT fetched_at, options FROM courses WHERE term = ?1 AND course_name = ?2",
+            params![term, course_name],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?;
+        Ok(row.and_then(|(fetched_at, options)| {
+            Some(CachedCourse {
+                options: serde_json::from_str(&options).ok()?,
+                fetched_at: DateTime::parse_from_rfc3339(&fetched_at).ok()?.with_timezone(&Utc)
+            })
+        }))
+    }
+
+    // every term with a cached course, the latest first
+    pub fn terms(&self) -> rusqlite::Result<Vec<String>> {
+        let mut statement = self.connection.prepare("SELECT DISTINCT term FROM courses ORDER BY term DESC")?;
+        let terms = statement.query_map([], |row| row.get(0))?;
+        terms.collect()
+    }
+
+    // every course cached for a term, sorted by name
+    pub fn courses_in_term(&self, term: &str) -> rusqlite::Result<Vec<String>> {
+        let mut statement = self.connection.prepare("SELECT course_name FROM courses WHERE term = ?1 ORDER BY course_name")?;
+        let course_names = statement.query_map(params![term], |row| row.get(0))?;
+        course_names.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::course::course_manager::Section;
+
+    #[test]
+    fn test_catalog_round_trip_and_freshness() {
+        let catalog = CourseCatalog::open(":memory:").unwrap();
+        let option = Course::create_course_time(
+            "CSCI3100".to_string(),
+            "Dr. LAM Tak Kei".to_string(),
+            vec![Section::parse_header("( 8249 ) - - LEC").unwrap()]
+        );
+        let fetched_at = DateTime::parse_from_rfc3339("2025-01-06T09:00:00Z").unwrap().with_timezone(&Utc);
+        catalog.save("2024-25 Term 2", "CSCI3100", std::slice::from_ref(&option), fetched_at).unwrap();
+
+        let cached = catalog.load("2024-25 Term 2", "CSCI3100").unwrap().unwrap();
+        assert_eq!(cached.options, vec![option]);
+        assert_eq!(cached.fetched_at, fetched_at);
+        assert!(catalog.load("2025-26 Term 1", "CSCI3100").unwrap().is_none());
+        assert_eq!(catalog.courses_in_term("2024-25 Term 2").unwrap(), vec!["CSCI3100".to_string()]);
+        catalog.save("2025-26 Term 1", "ELTU3502", &[], fetched_at).unwrap();
+        assert_eq!(catalog.terms().unwrap(), vec!["2025-26 Term 1".to_string(), "2024-25 Term 2".to_string()]);
+
+        let policy = FreshnessPolicy { max_age: Duration::hours(24), force_refresh: false };
+        assert!(policy.is_fresh(&cached, fetched_at + Duration::hours(23)));
+        assert!(!policy.is_fresh(&cached, fetched_at + Duration::hours(25)));
+        assert!(!FreshnessPolicy { force_refresh: true, ..policy }.is_fresh(&cached, fetched_at));
+    }
+}
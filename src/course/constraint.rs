@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use chrono::NaiveTime;
+use super::campus::CampusMap;
+use super::course_manager::{Course, EnrollmentMode};
+use super::meeting::{Meeting, Weekday};
+
+// A hard rule the backtracking scheduler consults before placing a course option.
+// `time_slot` contains the meetings already placed in the partial schedule, keyed by weekday.
+pub trait Constraint: Debug + Send + Sync {
+    fn name(&self) -> String;
+    fn allows(&self, option: &Course, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> bool;
+}
+
+fn day_names(days: &[Weekday]) -> String {
+    days.iter().map(|day| day.name()).collect::<Vec<_>>().join(", ")
+}
+
+// whether `meeting` lies wholly between `start` and `end` of its day
+fn fits_between(meeting: &Meeting, start: NaiveTime, end: NaiveTime) -> bool {
+    Meeting::new(meeting.weekday, start, end).is_some_and(|window| window.contains_meeting(meeting))
+}
+
+// the last moment of a day, no class runs past it
+fn end_of_day() -> NaiveTime {
+    NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap()
+}
+
+#[derive(Debug, Clone)]
+pub struct EarliestStartTime(pub NaiveTime);
+
+impl Constraint for EarliestStartTime {
+    fn name(&self) -> String {
+        format!("No class before {}", self.0.format("%H:%M"))
+    }
+
+    fn allows(&self, option: &Course, _time_slot: &HashMap<Weekday, Vec<Meeting>>) -> bool {
+        option.meetings().iter().all(|meeting| fits_between(meeting, self.0, end_of_day()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LatestEndTime(pub NaiveTime);
+
+impl Constraint for LatestEndTime {
+    fn name(&self) -> String {
+        format!("No class after {}", self.0.format("%H:%M"))
+    }
+
+    fn allows(&self, option: &Course, _time_slot: &HashMap<Weekday, Vec<Meeting>>) -> bool {
+        option.meetings().iter().all(|meeting| fits_between(meeting, NaiveTime::MIN, self.0))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ForbiddenDays(pub Vec<Weekday>);
+
+impl Constraint for ForbiddenDays {
+    fn name(&self) -> String {
+        format!("No class on {}", day_names(&self.0))
+    }
+
+    fn allows(&self, option: &Course, _time_slot: &HashMap<Weekday, Vec<Meeting>>) -> bool {
+        option.meetings().iter().all(|meeting| !self.0.contains(&meeting.weekday))
+    }
+}
+
+// A blocked period such as a part-time job, an empty `days` vector blocks the window on every day
+#[derive(Debug, Clone)]
+pub struct ForbiddenTimeWindow {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime
+}
+
+impl Constraint for ForbiddenTimeWindow {
+    fn name(&self) -> String {
+        let days = if self.days.is_empty() { "every day".to_string() } else { day_names(&self.days) };
+        format!("No class between {} and {} on {}", self.start.format("%H:%M"), self.end.format("%H:%M"), days)
+    }
+
+    fn allows(&self, option: &Course, _time_slot: &HashMap<Weekday, Vec<Meeting>>) -> bool {
+        option.meetings().iter().all(|meeting| {
+            let blocked_day = self.days.is_empty() || self.days.contains(&meeting.weekday);
+            !(blocked_day && Meeting::new(meeting.weekday, self.start, self.end).is_some_and(|window| window.overlaps(meeting)))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaxDailyHours(pub f32);
+
+impl Constraint for MaxDailyHours {
+    fn name(&self) -> String {
+        format!("At most {} teaching hours per day", self.0)
+    }
+
+    fn allows(&self, option: &Course, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> bool {
+        let mut minutes_per_day: HashMap<Weekday, i64> = HashMap::new();
+        for meeting in option.meetings() {
+            *minutes_per_day.entry(meeting.weekday).or_insert(0) += meeting.duration().num_minutes();
+        }
+        minutes_per_day.into_iter().all(|(day, new_minutes)| {
+            let occupied_minutes: i64 = time_slot
+                .get(&day)
+                .map(|meetings| meetings.iter().map(|meeting| meeting.duration().num_minutes()).sum())
+                .unwrap_or(0);
+            (occupied_minutes + new_minutes) as f32 <= self.0 * 60.0
+        })
+    }
+}
+
+// Rejects back-to-back classes whose break is too short to walk between their buildings
+#[derive(Debug, Clone)]
+pub struct WalkingTime(pub Arc<CampusMap>);
+
+impl Constraint for WalkingTime {
+    fn name(&self) -> String {
+        "Enough time to walk between classes".to_string()
+    }
+
+    fn allows(&self, option: &Course, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> bool {
+        let meetings = option.meetings();
+        meetings.iter().all(|meeting| {
+            let placed = time_slot.get(&meeting.weekday).into_iter().flatten();
+            placed.chain(meetings.iter().copied()).all(|other| {
+                if other.weekday != meeting.weekday {
+                    true
+                }
+                else if other.end <= meeting.start {
+                    (meeting.start - other.end).num_minutes() >= self.0.walking_minutes(other, meeting)
+                }
+                else if meeting.end <= other.start {
+                    (other.start - meeting.end).num_minutes() >= self.0.walking_minutes(meeting, other)
+                }
+                else{
+                    true
+                }
+            })
+        })
+    }
+}
+
+// Keeps out options with a section whose seat state the chosen enrollment mode does not accept
+#[derive(Debug, Clone)]
+pub struct SeatAvailability(pub EnrollmentMode);
+
+impl Constraint for SeatAvailability {
+    fn name(&self) -> String {
+        match self.0 {
+            EnrollmentMode::OpenOnly => "Open sections only".to_string(),
+            EnrollmentMode::AllowWaitlist => "Open or waitlisted sections only".to_string(),
+            EnrollmentMode::IncludeClosed => "Any section".to_string()
+        }
+    }
+
+    fn allows(&self, option: &Course, _time_slot: &HashMap<Weekday, Vec<Meeting>>) -> bool {
+        self.0.admits(option.seat_status())
+    }
+}
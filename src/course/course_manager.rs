@@ -1,44 +1,393 @@
 use std::collections::HashMap;
-use chrono::NaiveTime;
 use std::hash::{Hash, Hasher};
+use serde::{Deserialize, Serialize};
+use super::meeting::Meeting;
 
-#[derive(Debug, Clone, Eq, PartialEq)]  
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Component {
+    Lecture,
+    Tutorial,
+    Lab,
+    Classwork,
+    Project,
+    Seminar,
+    Other(String)
+}
+
+impl Component {
+    pub fn parse(code: &str) -> Component {
+        match code.trim() {
+            "LEC" => Component::Lecture,
+            "TUT" => Component::Tutorial,
+            "LAB" => Component::Lab,
+            "CLW" => Component::Classwork,
+            "PRJ" => Component::Project,
+            "SEM" => Component::Seminar,
+            other => Component::Other(other.to_string())
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        match self {
+            Component::Lecture => "LEC",
+            Component::Tutorial => "TUT",
+            Component::Lab => "LAB",
+            Component::Classwork => "CLW",
+            Component::Project => "PRJ",
+            Component::Seminar => "SEM",
+            Component::Other(code) => code
+        }
+    }
+
+    // tutorials and labs hang off a primary component such as a lecture
+    pub fn is_primary(&self) -> bool {
+        !matches!(self, Component::Tutorial | Component::Lab)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SeatStatus {
+    Open,
+    Waitlist,
+    Closed
+}
+
+impl SeatStatus {
+    // the status line CUSIS prints on every class row, e.g. "Open", "Closed" or "Wait List"
+    pub fn parse(line: &str) -> Option<SeatStatus> {
+        let line = line.trim().to_lowercase();
+        if line.starts_with("open") {
+            Some(SeatStatus::Open)
+        }
+        else if line.starts_with("wait") {
+            Some(SeatStatus::Waitlist)
+        }
+        else if line.starts_with("closed") || line.starts_with("full") {
+            Some(SeatStatus::Closed)
+        }
+        else{
+            None
+        }
+    }
+}
+
+// Seat state of a section, the counts are None when CUSIS did not show them
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Seats {
+    pub status: SeatStatus,
+    pub capacity: Option<u32>,
+    pub enrolled: Option<u32>,
+    pub waitlist: Option<u32>
+}
+
+impl Default for Seats {
+    fn default() -> Seats {
+        Seats {
+            status: SeatStatus::Open,
+            capacity: None,
+            enrolled: None,
+            waitlist: None
+        }
+    }
+}
+
+impl Seats {
+    // short label shown next to the section in the timetable, e.g. "2 left", "Waitlist 3" or "Closed"
+    pub fn badge(&self) -> String {
+        match self.status {
+            SeatStatus::Open => match (self.capacity, self.enrolled) {
+                (Some(capacity), Some(enrolled)) => format!("{} left", capacity.saturating_sub(enrolled)),
+                _ => "Open".to_string()
+            },
+            SeatStatus::Waitlist => match self.waitlist {
+                Some(waitlist) => format!("Waitlist {}", waitlist),
+                None => "Waitlist".to_string()
+            },
+            SeatStatus::Closed => "Closed".to_string()
+        }
+    }
+}
+
+// Which seat states the scheduler may pick sections from
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EnrollmentMode {
+    OpenOnly,
+    AllowWaitlist,
+    // closed sections are kept for planning a future term or a swap later on
+    IncludeClosed
+}
+
+impl EnrollmentMode {
+    pub fn admits(&self, status: SeatStatus) -> bool {
+        match self {
+            EnrollmentMode::OpenOnly => status == SeatStatus::Open,
+            EnrollmentMode::AllowWaitlist => status != SeatStatus::Closed,
+            EnrollmentMode::IncludeClosed => true
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    pub component: Component,
+    pub class_number: u32,
+    // e.g. "T01" or "BC01", empty for an unlabelled lecture
+    pub label: String,
+    pub meetings: Vec<Meeting>,
+    pub seats: Seats
+}
+
+impl Hash for Section {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.component.hash(state);
+        self.class_number.hash(state);
+        self.label.hash(state);
+    }
+}
+
+impl Section {
+    pub fn new(component: Component, class_number: u32, label: String) -> Section {
+        Section {
+            component,
+            class_number,
+            label,
+            meetings: vec![],
+            seats: Seats::default()
+        }
+    }
+
+    // parses a CUSIS class header such as "( 8810 ) -T01 - TUT" or "( 4980 ) BC01 - CLW"
+    pub fn parse_header(line: &str) -> Option<Section> {
+        let rest = line.trim().strip_prefix('(')?;
+        let (class_number, rest) = rest.split_once(')')?;
+        let class_number = class_number.trim().parse::<u32>().ok()?;
+        let (label, component) = rest.rsplit_once(" - ")?;
+        let label = label.trim().trim_start_matches('-').trim().to_string();
+        if component.trim().is_empty() {
+            return None;
+        }
+        Some(Section::new(Component::parse(component), class_number, label))
+    }
+
+    // e.g. "LEC" or "TUT T01"
+    pub fn display_name(&self) -> String {
+        if self.label.is_empty() {
+            self.component.code().to_string()
+        }
+        else{
+            format!("{} {}", self.component.code(), self.label)
+        }
+    }
+}
+
+// One enrollable combination of a course: a primary section together with one section of every other required component
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Course{
-    //the struct owns the values instead of a reference 
+    //the struct owns the values instead of a reference
     pub course_name: String,
-    pub datetime: HashMap<String, Vec<Vec<NaiveTime>>>,
     pub instructor: String,
-    pub class_code: String,
-    pub tutorial_code: String,
-    pub lab_code: String
+    pub sections: Vec<Section>
 }
 
 impl Hash for Course {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.course_name.hash(state);
         self.instructor.hash(state);
-        self.class_code.hash(state);
-        self.tutorial_code.hash(state);
-        self.lab_code.hash(state);
+        self.sections.hash(state);
     }
 }
- 
+
 impl Course{
     pub fn create_course_time(
         course_name: String,
-        datetime: HashMap<String, Vec<Vec<NaiveTime>>>, 
-        instructor: String, 
-        class_code: String, 
-        tutorial_code: String,
-        lab_code: String
+        instructor: String,
+        sections: Vec<Section>
     ) -> Course {
         Course {
             course_name,
-            datetime,
             instructor,
-            class_code,
-            tutorial_code,
-            lab_code
+            sections
+        }
+    }
+
+    // meetings of every section, a meeting shared by two sections is only yielded once
+    pub fn meetings(&self) -> Vec<&Meeting> {
+        let mut meetings: Vec<&Meeting> = vec![];
+        for meeting in self.sections.iter().flat_map(|section| section.meetings.iter()) {
+            if !meetings.contains(&meeting) {
+                meetings.push(meeting);
+            }
         }
+        meetings
+    }
+
+    #[cfg(test)]
+    pub fn section(&self, component: &Component) -> Option<&Section> {
+        self.sections.iter().find(|section| &section.component == component)
+    }
+
+    pub fn class_numbers(&self) -> Vec<u32> {
+        self.sections.iter().map(|section| section.class_number).collect()
+    }
+
+    // an option can only be enrolled as far as its most restricted section allows
+    pub fn seat_status(&self) -> SeatStatus {
+        self.sections.iter().map(|section| section.seats.status).max().unwrap_or(SeatStatus::Open)
+    }
+}
+
+// (instructor, sections of the option, meetings of the whole option)
+pub type ListedOption = (String, Vec<Section>, Vec<Meeting>);
+
+// Every class option CUSIS lists for a course
+#[derive(Debug, Clone, Default)]
+pub struct CourseOffering {
+    pub course_name: String,
+    pub options: Vec<ListedOption>
+}
+
+impl CourseOffering {
+    pub fn new(course_name: String) -> CourseOffering {
+        CourseOffering {
+            course_name,
+            options: vec![]
+        }
+    }
+
+    pub fn add_option(&mut self, instructor: String, sections: Vec<Section>, meetings: Vec<Meeting>) {
+        if !sections.is_empty() {
+            self.options.push((instructor, sections, meetings));
+        }
+    }
+
+    // CUSIS lists the meetings of a whole option, a section is given the meetings shared by every option it appears in
+    fn section_meetings(&self, class_number: u32) -> Vec<Meeting> {
+        let mut options = self.options.iter()
+            .filter(|(_, sections, _)| sections.iter().any(|section| section.class_number == class_number))
+            .map(|(_, _, meetings)| meetings);
+        let mut shared = match options.next() {
+            Some(meetings) => meetings.clone(),
+            None => return vec![]
+        };
+        for meetings in options {
+            shared.retain(|meeting| meetings.contains(meeting));
+        }
+        shared
+    }
+
+    // Expands every primary section with each choice of the other components listed alongside it,
+    // so a lecture with three tutorials becomes three combinations the scheduler can choose from
+    pub fn combinations(&self) -> Vec<Course> {
+        let mut primary_order: Vec<u32> = vec![];
+        let mut groups: HashMap<u32, Vec<&ListedOption>> = HashMap::new();
+        for option in &self.options {
+            let primary = option.1.iter().find(|section| section.component.is_primary()).unwrap_or(&option.1[0]);
+            if !groups.contains_key(&primary.class_number) {
+                primary_order.push(primary.class_number);
+            }
+            groups.entry(primary.class_number).or_default().push(option);
+        }
+
+        let mut combinations = vec![];
+        for primary_number in primary_order {
+            let group = &groups[&primary_number];
+            let instructor = group[0].0.clone();
+            let mut primary = group[0].1.iter().find(|section| section.class_number == primary_number).unwrap().clone();
+            primary.meetings = self.section_meetings(primary_number);
+            // a lecture is listed once per tutorial row, it is as open as its most open row
+            if let Some(seats) = group.iter()
+                .flat_map(|(_, sections, _)| sections.iter())
+                .filter(|section| section.class_number == primary_number)
+                .map(|section| section.seats)
+                .min_by_key(|seats| seats.status) {
+                primary.seats = seats;
+            }
+
+            // distinct sections of every other component, in the order CUSIS lists them
+            let mut choices: Vec<(Component, Vec<Section>)> = vec![];
+            for (_, sections, _) in group {
+                for section in sections.iter().filter(|section| section.class_number != primary_number) {
+                    let index = match choices.iter().position(|(component, _)| *component == section.component) {
+                        Some(index) => index,
+                        None => {
+                            choices.push((section.component.clone(), vec![]));
+                            choices.len() - 1
+                        }
+                    };
+                    if choices[index].1.iter().any(|choice| choice.class_number == section.class_number) {
+                        continue;
+                    }
+                    let mut section = section.clone();
+                    section.meetings = self.section_meetings(section.class_number);
+                    section.meetings.retain(|meeting| !primary.meetings.contains(meeting));
+                    choices[index].1.push(section);
+                }
+            }
+
+            let mut partial_combinations: Vec<Vec<Section>> = vec![vec![primary]];
+            for (_, sections) in &choices {
+                partial_combinations = partial_combinations.into_iter()
+                    .flat_map(|partial| {
+                        sections.iter().map(move |section| {
+                            let mut combination = partial.clone();
+                            combination.push(section.clone());
+                            combination
+                        })
+                    })
+                    .collect();
+            }
+            for sections in partial_combinations {
+                combinations.push(Course::create_course_time(self.course_name.clone(), instructor.clone(), sections));
+            }
+        }
+        combinations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+    use crate::course::meeting::Weekday;
+
+    fn meeting(weekday: Weekday, start: (u32, u32), end: (u32, u32)) -> Meeting {
+        Meeting::new(weekday, NaiveTime::from_hms_opt(start.0, start.1, 00).unwrap(), NaiveTime::from_hms_opt(end.0, end.1, 00).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_section_header() {
+        let lecture = Section::parse_header("( 8232 ) - - LEC").unwrap();
+        assert_eq!((lecture.component, lecture.class_number, lecture.label), (Component::Lecture, 8232, "".to_string()));
+        let tutorial = Section::parse_header("( 8810 ) -T01 - TUT").unwrap();
+        assert_eq!((tutorial.component, tutorial.class_number, tutorial.label), (Component::Tutorial, 8810, "T01".to_string()));
+        let classwork = Section::parse_header("( 4980 ) BC01 - CLW").unwrap();
+        assert_eq!((classwork.component, classwork.class_number, classwork.label), (Component::Classwork, 4980, "BC01".to_string()));
+        assert!(Section::parse_header("Professor Lauren Marcelyn PICK").is_none());
+    }
+
+    #[test]
+    fn test_offering_combinations_split_tutorials() {
+        let mut offering = CourseOffering::new("CSCI3180".to_string());
+        let lecture_time = meeting(Weekday::Monday, (14, 30), (16, 15));
+        offering.add_option(
+            "Professor Lauren Marcelyn PICK".to_string(),
+            vec![Section::parse_header("( 8232 ) - - LEC").unwrap(), Section::parse_header("( 8810 ) -T01 - TUT").unwrap()],
+            vec![lecture_time.clone(), meeting(Weekday::Tuesday, (15, 30), (16, 15))]
+        );
+        offering.add_option(
+            "Professor Lauren Marcelyn PICK".to_string(),
+            vec![Section::parse_header("( 8232 ) - - LEC").unwrap(), Section::parse_header("( 8885 ) -T02 - TUT").unwrap()],
+            vec![lecture_time.clone(), meeting(Weekday::Wednesday, (16, 30), (17, 15))]
+        );
+
+        let combinations = offering.combinations();
+        assert_eq!(combinations.len(), 2);
+        assert_eq!(combinations[0].class_numbers(), vec![8232, 8810]);
+        assert_eq!(combinations[1].class_numbers(), vec![8232, 8885]);
+        let lecture = combinations[1].section(&Component::Lecture).unwrap();
+        assert_eq!(lecture.meetings, vec![lecture_time]);
+        let tutorial = combinations[1].section(&Component::Tutorial).unwrap();
+        assert_eq!(tutorial.meetings, vec![meeting(Weekday::Wednesday, (16, 30), (17, 15))]);
+        assert_eq!(combinations[1].meetings().len(), 2);
     }
 }
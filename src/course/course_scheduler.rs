@@ -1,18 +1,28 @@
-use crate::VALID_SCL_DAYS;
-
 use super::course_manager::Course;
+use super::meeting::{Meeting, Weekday};
+use super::constraint::Constraint;
+use super::objective::{Objective, ObjectiveContribution};
+use super::score_report::ScoreReport;
+use super::infeasibility::InfeasibilityReport;
 use dashmap::DashMap;
-use core::time;
 //use tokio::time;
 use std::{collections::{HashMap, HashSet}};
-use chrono::NaiveTime;
+use std::sync::Arc;
+use chrono::{NaiveTime, Timelike};
+// the timetable, enrollment codes and score report of one ranked candidate
+pub type RankedSchedule = (Vec<String>, Vec<(String, Vec<String>)>, ScoreReport);
+
 #[derive(Debug)]
 #[derive(Clone)]
 pub struct Scheduler {
     scheduled_courses_name: Vec<String>,
-    time_slot: HashMap<String,  Vec<Vec<NaiveTime>>>,
+    time_slot: HashMap<Weekday, Vec<Meeting>>,
     scheduled_course_details: HashSet<Course>,
-    candidate_solutions: Vec<(i64, HashSet<Course>)>,
+    candidate_solutions: Vec<(i64, ScoreReport, HashSet<Course>)>,
+    // courses the student is already enrolled in, their meetings stay in time_slot for good
+    commitments: Vec<Course>,
+    constraints: Vec<Arc<dyn Constraint>>,
+    objectives: Vec<(i64, Arc<dyn Objective>)>,
     index: i64
 }
 impl Scheduler {
@@ -20,155 +30,172 @@ impl Scheduler {
         Scheduler {
             scheduled_courses_name: vec![],
             time_slot: 
-                crate::VALID_SCL_DAYS.iter()
-                .map(|&day| (day.to_string(), Vec::new()))
+                Weekday::SCHOOL_DAYS.iter()
+                .map(|&day| (day, Vec::new()))
                 .collect(),
             scheduled_course_details: HashSet::new(),
             candidate_solutions: vec![],
-            index: 0
+            commitments: vec![],
+            constraints: vec![],
+            objectives: vec![],
+            index: -1
         }
     }
 
-    fn find_timetable_index(time_str: String, day: &str) -> i32{
-        let time_parts = time_str.split(":").collect::<Vec<&str>>();
-        let index = time_parts[0].parse::<i32>().unwrap() - 9;
-        match day{
-            "Monday" => {
-                return index * 6 + 1;
-            },
-            "Tuesday" => {
-                return index * 6 + 2;
-            },
-            "Wednesday" => {
-                return index * 6 + 3;
-            },
-            "Thursday" => {
-                return index * 6 + 4;
-            },
-            "Friday" => {
-                return index * 6 + 5;
-            },
-            "Saturday" => {
-                return index * 6 + 6;
-            },
-            _ =>{
-                return -1;
-            }
+    // register a hard constraint that every placed course option has to satisfy
+    pub fn add_constraint<C: Constraint + 'static>(&mut self, constraint: C) {
+        self.constraints.push(Arc::new(constraint));
+    }
+
+    // register a soft preference, its penalty is multiplied by `weight` when ranking candidates
+    pub fn add_objective<O: Objective + 'static>(&mut self, objective: O, weight: i64) {
+        self.objectives.push((weight, Arc::new(objective)));
+    }
+
+    // locks an enrolled course into the timetable, generated schedules are only built around its meetings
+    pub fn add_commitment(&mut self, course: Course) {
+        for meeting in course.meetings() {
+            self.time_slot.entry(meeting.weekday).or_default().push(meeting.clone());
         }
-        
+        self.commitments.push(course);
+    }
+
+    // a candidate together with the committed courses, which is the week the student would actually have
+    fn with_commitments(&self, course_set: &HashSet<Course>) -> HashSet<Course> {
+        course_set.iter().chain(self.commitments.iter()).cloned().collect()
+    }
+
+    // the timetable has a column per school day and a row per hour from 09:00 to 22:00,
+    // meetings outside the grid have no cell
+    fn find_timetable_index(meeting: &Meeting) -> Option<usize>{
+        let column = Weekday::SCHOOL_DAYS.iter().position(|day| *day == meeting.weekday)?;
+        let grid = Meeting::new(meeting.weekday, NaiveTime::from_hms_opt(9, 0, 0)?, NaiveTime::from_hms_opt(23, 0, 0)?)?;
+        if !grid.contains(meeting.start) {
+            return None;
+        }
+        Some((meeting.start.hour() as usize - 9) * Weekday::SCHOOL_DAYS.len() + column)
     } 
     fn reduce_course_set_to_timetable_string(course_set: &HashSet<Course>) -> Vec<String>{
         let mut res = vec![String::from(""); 84];
-        // use the res to store the class data in the format "Course code\nTime\nInstructor"
+        // use the res to store the class data in the format "Course code Component [Seats]\nTime\nRoom\nInstructor"
         for course in course_set{
-            let timeslot = &course.datetime;
             let instructor = &course.instructor;
             let course_name= &course.course_name;
-            for day in VALID_SCL_DAYS{
-                if let Some(class_time_vec) = timeslot.get(day){
-                    for class_time in class_time_vec{
-                        let mut course_data_str = String::from("");
-                        let start_time = class_time[0].to_string();
-                        course_data_str.push_str(course_name);
-                        course_data_str.push_str("\n");
-                        course_data_str.push_str(&class_time[0].format("%H:%M").to_string());
-                        course_data_str.push_str(&" - ");
-                        course_data_str.push_str(&class_time[1].format("%H:%M").to_string());
-                        course_data_str.push_str("\n");
-                        course_data_str.push_str(instructor);
-                        let index = Scheduler::find_timetable_index(start_time, day);
-                        res[index as usize] = course_data_str;
+            for section in &course.sections{
+                for meeting in &section.meetings{
+                    let mut course_data_str = String::from("");
+                    course_data_str.push_str(course_name);
+                    course_data_str.push(' ');
+                    course_data_str.push_str(&section.display_name());
+                    course_data_str.push_str(&format!(" [{}]", section.seats.badge()));
+                    course_data_str.push('\n');
+                    course_data_str.push_str(&meeting.time_range());
+                    course_data_str.push('\n');
+                    if !meeting.room.is_empty(){
+                        course_data_str.push_str(&meeting.room);
+                        course_data_str.push('\n');
+                    }
+                    course_data_str.push_str(instructor);
+                    if let Some(index) = Scheduler::find_timetable_index(meeting){
+                        res[index] = course_data_str;
                     }
                 }
             }
         }
         res
     }
-    pub fn get_next_schedule(&mut self, direction: i8) -> Option<(Vec<String>, Vec<(String, Vec<String>)>)>{
-        if self.index >= 0 && self.index < self.candidate_solutions.len() as i64 && self.candidate_solutions.len() > 0 {
-            let schedule = &self.candidate_solutions[self.index as usize].1;
-            let result = Some((Scheduler::reduce_course_set_to_timetable_string(schedule), self.reduce_course_set_to_course_and_choice_vec(&schedule)));
-            match direction{
-                1 => {
-                    self.index += 1
-                },
-                0 => {
-                    self.index -= 1
-                },
-                _ => {}
-            }
-           result
+    // moves to the next (direction 1) or previous (direction 0) ranked candidate and returns its timetable,
+    // enrollment codes and score report, the index starts before the best candidate
+    pub fn get_next_schedule(&mut self, direction: i8) -> Option<RankedSchedule>{
+        let target = match direction{
+            1 => self.index + 1,
+            0 => self.index - 1,
+            _ => self.index
+        };
+        if target >= 0 && target < self.candidate_solutions.len() as i64 {
+            self.index = target;
+            let (_, report, schedule) = &self.candidate_solutions[target as usize];
+            Some((Scheduler::reduce_course_set_to_timetable_string(&self.with_commitments(schedule)), self.reduce_course_set_to_course_and_choice_vec(schedule), report.clone()))
         }
         else{
             None
         }
     }
-    fn transform_course_set(&self, solution: &HashSet<Course>) -> HashMap<String,  Vec<Vec<NaiveTime>>>{
-        let mut temp_time_slot: HashMap<String,  Vec<Vec<NaiveTime>>> =  
-            crate::VALID_SCL_DAYS.iter()
-            .map(|&day| (day.to_string(), Vec::new()))
+
+    // enrollment codes of the candidate currently on display, None before the first get_next_schedule
+    pub fn get_current_schedule(&self) -> Option<Vec<(String, Vec<String>)>> {
+        let (_, _, schedule) = self.candidate_solutions.get(usize::try_from(self.index).ok()?)?;
+        Some(self.reduce_course_set_to_course_and_choice_vec(schedule))
+    }
+
+    pub fn get_current_rank(&self) -> i64 {
+        self.index
+    }
+
+    pub fn get_candidate_count(&self) -> usize {
+        self.candidate_solutions.len()
+    }
+
+    // explains why the candidate at `rank` is placed ahead of the one ranked right after it
+    pub fn explain_rank(&self, rank: usize) -> Option<String> {
+        let (_, report, _) = self.candidate_solutions.get(rank)?;
+        match self.candidate_solutions.get(rank + 1) {
+            Some((_, next_report, _)) => Some(format!(
+                "Why #{} ranks above #{}:\n{}",
+                rank + 1,
+                rank + 2,
+                report.explain_against(next_report)
+            )),
+            None => Some(format!("#{} is the last candidate", rank + 1))
+        }
+    }
+
+    fn transform_course_set(&self, solution: &HashSet<Course>) -> HashMap<Weekday, Vec<Meeting>>{
+        let mut temp_time_slot: HashMap<Weekday, Vec<Meeting>> =  
+            Weekday::SCHOOL_DAYS.iter()
+            .map(|&day| (day, Vec::new()))
             .collect();
         for course in solution{
-            let date_time: &HashMap<String, Vec<Vec<NaiveTime>>> = &course.datetime;
-            for (day, class_period) in date_time{
-                for class_time in class_period{
-                    let start_time = &class_time[0];
-                    temp_time_slot
-                    .entry(day.to_string())
-                    .and_modify(|slots| {
-                        let pos = slots
-                            .binary_search_by_key(&start_time, |slot| &slot[0])
-                            .unwrap_or_else(|i| i);
-                        slots.insert(pos, class_time.clone());
-                    })
-                    .or_insert_with(|| class_period.clone());
-                }
+            for meeting in course.meetings(){
+                let slots = temp_time_slot.entry(meeting.weekday).or_default();
+                let pos = slots
+                    .binary_search_by_key(&meeting.start, |slot| slot.start)
+                    .unwrap_or_else(|i| i);
+                slots.insert(pos, meeting.clone());
             }
         }
         temp_time_slot
     }
 
-    fn cal_fitness_score(&self, solution: &HashSet<Course>, day_off_preference: String) -> i64{
-        let mut score = 0;
+    // weighted sum of every registered objective, lower scores rank first
+    fn cal_fitness_score(&self, solution: &HashSet<Course>) -> (i64, ScoreReport){
         let time_slot = self.transform_course_set(solution);
-        match time_slot.get(&day_off_preference){
-            Some(courses) => {
-                score +=  (courses.len())as i64 * 100;
-            },
-            None => {
-                score -= 200;
-            }
-        }
-        let vec: Vec<Vec<Vec<NaiveTime>>> = time_slot.into_values().into_iter().filter(|x|x.len() > 0).collect();
-        
-        for day in vec{
-            if day.len() > 1{
-                for i in 1..day.len(){
-                    let last_period = &day[i-1];
-                    let current_period =   &day[i];
-                    let time_gap_between_course = current_period[0] - last_period[1];
-                    score += time_gap_between_course.num_minutes()
+        let contributions: Vec<ObjectiveContribution> = self.objectives.iter()
+            .map(|(weight, objective)| {
+                let penalty = objective.penalty(&time_slot);
+                ObjectiveContribution {
+                    name: objective.name(),
+                    weight: *weight,
+                    penalty,
+                    score: weight * penalty
                 }
-            }
-            else if day.len() == 1{
-                score += 20;
-            }
-        }
-        score
+            })
+            .collect();
+        let report = ScoreReport::new(&time_slot, contributions);
+        (report.total_score, report)
     }
    
 
     // a recursive backtracking algorithm 
-    pub fn generate_schedule(&mut self, course_dict: &DashMap<String, Vec<Course>>, day_off_preference: String){
-
-        if self.scheduled_courses_name.len() == course_dict.len(){
-            if !self.candidate_solutions.iter().any(|&(_, ref set)| set == &self.scheduled_course_details){
-                let solution_score = self.cal_fitness_score(&self.scheduled_course_details.clone(), day_off_preference.clone());
-                let index = self.candidate_solutions.binary_search_by_key(&solution_score, |score|score.0)
-                .unwrap_or_else(|i|i);
-                self.candidate_solutions.insert(index, (solution_score, self.scheduled_course_details.clone()));
-                return;
-            }
+    pub fn generate_schedule(&mut self, course_dict: &DashMap<String, Vec<Course>>){
+
+        if self.scheduled_courses_name.len() == course_dict.len()
+            && !self.candidate_solutions.iter().any(|(_, _, set)| set == &self.scheduled_course_details){
+            let (solution_score, report) = self.cal_fitness_score(&self.with_commitments(&self.scheduled_course_details));
+            let index = self.candidate_solutions.binary_search_by_key(&solution_score, |score|score.0)
+            .unwrap_or_else(|i|i);
+            self.candidate_solutions.insert(index, (solution_score, report, self.scheduled_course_details.clone()));
+            return;
         }
         
         // Loop throught the course_name-course key_pair 
@@ -178,69 +205,222 @@ impl Scheduler {
             if self.scheduled_courses_name.contains(course_name){
                 continue; // Skip already scheduled courses
             }
-            // i tells us which course option we have gone through 
-            for i in 0..course.len(){
+            for option in course.iter(){
                 // check if the course is already in schedule
-                let option =  &course[i];
-                let timeslot_dictionary = option.datetime.clone();
-                // For each couse option, there are several daytime combination which the program has to make sure all of them can fit into the schedule conflict-free
-                let mut can_schedule = true;
-                for (date, class_time_period)in &timeslot_dictionary{
-                        match self.time_slot.get(date){
-                        Some(occupied_timeslots) => {
-                            // if there is no conflicting class, add the class to the time_slot
-                            if !self.check_availability(&occupied_timeslots, &class_time_period){
-                                can_schedule = false;
-                            }
-                        },
-                        None => {}
-                        };
-                }
+                let meetings: Vec<Meeting> = option.meetings().into_iter().cloned().collect();
+                // For each couse option, there are several daytime combination which the program has to make sure all of them can fit into the schedule conflict-free,
+                // the registered hard constraints are then consulted against the partial schedule
+                let can_schedule = self.can_place(option, &self.time_slot, &self.constraints);
 
                 if can_schedule{
-                    for (day, class_time_period) in &timeslot_dictionary {
-                        if let Some(schedule) = self.time_slot.get_mut(day){
-                            for class in class_time_period{
-                                schedule.push(class.clone());
-                            }
-                        }
-                        self.scheduled_courses_name.push(course_name.clone());
-                        self.scheduled_course_details.insert(option.clone());
-                        self.generate_schedule(course_dict, day_off_preference.clone()); 
-                        self.scheduled_courses_name.pop();
-                        self.scheduled_course_details.remove(option);
-                        for (day, class_time_period) in &timeslot_dictionary {
-                            for scheduled_timeslot in class_time_period {
-                                if let Some(org_timeslot) = self.time_slot.get_mut(day){
-                                    if org_timeslot.iter().any(|time| time == scheduled_timeslot){
-                                        org_timeslot.retain(|time| time != scheduled_timeslot);
-                                    }
-                                }
+                    for meeting in &meetings {
+                        self.time_slot.entry(meeting.weekday).or_default().push(meeting.clone());
+                    }
+                    self.scheduled_courses_name.push(course_name.clone());
+                    self.scheduled_course_details.insert(option.clone());
+                    self.generate_schedule(course_dict);
+                    self.scheduled_courses_name.pop();
+                    self.scheduled_course_details.remove(option);
+                    for meeting in &meetings {
+                        if let Some(org_timeslot) = self.time_slot.get_mut(&meeting.weekday){
+                            if let Some(pos) = org_timeslot.iter().position(|time| time == meeting){
+                                org_timeslot.remove(pos);
                             }
                         }
                     }
                 }
             }
+            // courses are placed in a fixed order, the remaining ones are handled deeper in the recursion
+            break;
         }
     }
    
-    fn check_availability(
+    fn can_place(&self, option: &Course, time_slot: &HashMap<Weekday, Vec<Meeting>>, constraints: &[Arc<dyn Constraint>]) -> bool {
+        for meeting in option.meetings(){
+            if let Some(occupied_timeslots) = time_slot.get(&meeting.weekday){
+                if !self.check_availability(occupied_timeslots, std::slice::from_ref(meeting)){
+                    return false;
+                }
+            }
+        }
+        constraints.iter().all(|constraint| constraint.allows(option, time_slot))
+    }
+
+    // depth first search that stops at the first complete placement of `courses`
+    fn can_place_all(
         &self,
-        occupied_timeslots: &Vec<Vec<NaiveTime>>,
-        new_timeslots: &Vec<Vec<NaiveTime>>,
+        courses: &[&(String, Vec<Course>)],
+        time_slot: &mut HashMap<Weekday, Vec<Meeting>>,
+        constraints: &[Arc<dyn Constraint>]
     ) -> bool {
-        for new_slot in new_timeslots {
-            let new_start = new_slot[0];
-            let new_end = new_slot[1];
-            for occupied_slot in occupied_timeslots {
-                let occupied_start = occupied_slot[0];
-                let occupied_end = occupied_slot[1];
-                if new_start < occupied_end && new_end > occupied_start {
-                    return false;
+        let Some((course, remaining_courses)) = courses.split_first() else {
+            return true;
+        };
+        for option in &course.1 {
+            if !self.can_place(option, time_slot, constraints){
+                continue;
+            }
+            let meetings = option.meetings();
+            for meeting in &meetings {
+                time_slot.entry(meeting.weekday).or_default().push((*meeting).clone());
+            }
+            let placed = self.can_place_all(remaining_courses, time_slot, constraints);
+            for meeting in &meetings {
+                if let Some(org_timeslot) = time_slot.get_mut(&meeting.weekday){
+                    if let Some(pos) = org_timeslot.iter().position(|time| time == *meeting){
+                        org_timeslot.remove(pos);
+                    }
+                }
+            }
+            if placed {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn is_feasible(&self, courses: &[&(String, Vec<Course>)], constraints: &[Arc<dyn Constraint>]) -> bool {
+        let mut time_slot = self.time_slot.clone();
+        self.can_place_all(courses, &mut time_slot, constraints)
+    }
+
+    // (first component, second component, "Monday 12:30") for every period where two course options overlap
+    fn overlapping_slots(first: &Course, second: &Course) -> HashSet<(String, String, String)> {
+        let mut slots = HashSet::new();
+        for first_section in &first.sections {
+            for second_section in &second.sections {
+                for first_meeting in &first_section.meetings {
+                    for second_meeting in second_section.meetings.iter().filter(|second_meeting| first_meeting.overlaps(second_meeting)) {
+                        slots.insert((
+                            first_section.component.code().to_string(),
+                            second_section.component.code().to_string(),
+                            format!("{} {}", first_meeting.weekday, first_meeting.start.max(second_meeting.start).format("%H:%M"))
+                        ));
+                    }
+                }
+            }
+        }
+        slots
+    }
+
+    // Finds a minimal subset of courses that cannot be scheduled together and suggests how to resolve it,
+    // returns None when a conflict-free schedule exists
+    pub fn diagnose_infeasibility(&self, course_dict: &DashMap<String, Vec<Course>>) -> Option<InfeasibilityReport> {
+        let mut courses: Vec<(String, Vec<Course>)> = course_dict.iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        courses.sort_by(|a, b| a.0.cmp(&b.0));
+        let all_courses: Vec<&(String, Vec<Course>)> = courses.iter().collect();
+        if self.is_feasible(&all_courses, &self.constraints) {
+            return None;
+        }
+
+        // deletion filter: a course is left out for good whenever the rest still cannot be scheduled
+        let mut core = all_courses.clone();
+        let mut i = 0;
+        while i < core.len() {
+            let mut reduced_core = core.clone();
+            reduced_core.remove(i);
+            if self.is_feasible(&reduced_core, &self.constraints) {
+                i += 1;
+            }
+            else{
+                core = reduced_core;
+            }
+        }
+
+        let mut conflicts = vec![];
+        if let [(course_name, options)] = core.as_slice() {
+            if options.is_empty() {
+                conflicts.push(format!("{} has no available section this term.", course_name));
+            }
+            else{
+                let broken_constraints: Vec<String> = self.constraints.iter()
+                    .filter(|constraint| options.iter().all(|option| !constraint.allows(option, &self.time_slot)))
+                    .map(|constraint| constraint.name())
+                    .collect();
+                if broken_constraints.is_empty() {
+                    conflicts.push(format!("No option of {} satisfies every constraint at once.", course_name));
+                }
+                else{
+                    conflicts.push(format!("Every option of {} breaks: {}.", course_name, broken_constraints.join(", ")));
+                }
+            }
+        }
+        for (index, (first_name, first_options)) in core.iter().enumerate() {
+            for (second_name, second_options) in core.iter().skip(index + 1) {
+                let mut common_slots: Option<HashSet<(String, String, String)>> = None;
+                let always_clash = first_options.iter().all(|first| {
+                    second_options.iter().all(|second| {
+                        let slots = Scheduler::overlapping_slots(first, second);
+                        if slots.is_empty() {
+                            return false;
+                        }
+                        common_slots = Some(match common_slots.take() {
+                            Some(common) => common.intersection(&slots).cloned().collect(),
+                            None => slots
+                        });
+                        true
+                    })
+                });
+                if !always_clash {
+                    continue;
+                }
+                let mut common_slots: Vec<(String, String, String)> = common_slots.unwrap_or_default().into_iter().collect();
+                common_slots.sort();
+                if common_slots.is_empty() {
+                    conflicts.push(format!("{} and {} clash in every combination of their options.", first_name, second_name));
+                }
+                for (first_component, second_component, slot) in common_slots {
+                    conflicts.push(format!("{} {} and {} {} overlap on {} in every option.", first_name, first_component, second_name, second_component, slot));
                 }
             }
         }
-        true
+        if conflicts.is_empty() {
+            let core_names: Vec<String> = core.iter().map(|(course_name, _)| course_name.clone()).collect();
+            if self.is_feasible(&core, &[]) {
+                conflicts.push(format!("{} only fit together once some constraints are relaxed.", core_names.join(", ")));
+            }
+            else{
+                conflicts.push(format!("Every combination of {} clashes somewhere.", core_names.join(", ")));
+            }
+        }
+
+        let mut suggestions = vec![];
+        for (course_name, _) in &core {
+            let remaining_courses: Vec<&(String, Vec<Course>)> = all_courses.iter()
+                .filter(|(other_name, _)| other_name != course_name)
+                .cloned()
+                .collect();
+            if self.is_feasible(&remaining_courses, &self.constraints) {
+                suggestions.push(format!("drop {}", course_name));
+            }
+        }
+        for index in 0..self.constraints.len() {
+            let mut relaxed_constraints = self.constraints.clone();
+            let relaxed = relaxed_constraints.remove(index);
+            if self.is_feasible(&all_courses, &relaxed_constraints) {
+                suggestions.push(format!("relax \"{}\"", relaxed.name()));
+            }
+        }
+        if suggestions.is_empty() {
+            let core_names: Vec<String> = core.iter().map(|(course_name, _)| course_name.clone()).collect();
+            suggestions.push(format!("drop one of {} and check the remaining courses again", core_names.join(", ")));
+        }
+
+        Some(InfeasibilityReport {
+            conflicting_courses: core.iter().map(|(course_name, _)| course_name.clone()).collect(),
+            conflicts,
+            suggestions
+        })
+    }
+
+    fn check_availability(
+        &self,
+        occupied_timeslots: &[Meeting],
+        new_timeslots: &[Meeting],
+    ) -> bool {
+        !new_timeslots.iter().any(|new_slot| occupied_timeslots.iter().any(|occupied_slot| new_slot.overlaps(occupied_slot)))
     }
 
     fn reduce_course_set_to_course_and_choice_vec(&self, course_set: &HashSet<Course>) -> Vec<(String, Vec<String>)>{
@@ -250,23 +430,29 @@ impl Scheduler {
         .map(
             |course|
             {
-                let mut codes = vec![course.class_code.clone()];
-                if !course.lab_code.is_empty() {
-                    codes.push(course.lab_code.clone());
-                }
-                if !course.tutorial_code.is_empty() {
-                    codes.push(course.tutorial_code.clone());
-                }
+                // class numbers of the primary section first, followed by its tutorial or lab
+                let codes = course.class_numbers().iter().map(|class_number| class_number.to_string()).collect();
                 (course.course_name.clone(), codes)
             }
         ).collect()
     }
 
     pub fn get_schedule_with_best_fitness_score(&self) -> Option<Vec<(String, Vec<String>)>>{
-        if self.candidate_solutions.len() > 0 {
-            println!("\nBest Generated Schedule: {:?}", &self.candidate_solutions[0].1);
-            //println!("{:?}", self.transform_course_set(&self.candidate_solutions[0].1));
-            Some(self.reduce_course_set_to_course_and_choice_vec(&self.candidate_solutions[0].1))
+        if !self.candidate_solutions.is_empty() {
+            println!("\nBest Generated Schedule:");
+            for course in &self.candidate_solutions[0].2 {
+                for section in &course.sections {
+                    for meeting in &section.meetings {
+                        println!("{} {} [{}]: {}", course.course_name, section.display_name(), section.seats.badge(), meeting);
+                    }
+                }
+            }
+            println!("{}", self.candidate_solutions[0].1.summary());
+            if let Some(explanation) = self.explain_rank(0) {
+                println!("{}", explanation);
+            }
+            //println!("{:?}", self.transform_course_set(&self.candidate_solutions[0].2));
+            Some(self.reduce_course_set_to_course_and_choice_vec(&self.candidate_solutions[0].2))
         }
         else{
             None
@@ -283,6 +469,200 @@ mod tests {
     use std::vec;
 
     use super::*;
+    use chrono::NaiveTime;
+    use crate::course::course_manager::Section;
+    use crate::course::campus::CampusMap;
+    use crate::course::constraint::{EarliestStartTime, ForbiddenTimeWindow, MaxDailyHours, WalkingTime};
+    use crate::course::objective::{AvoidEarlyMornings, MinimizeGapTime, MinimizeWalking, PreferDayOff};
+
+    // builds a course option from CUSIS class headers, every meeting is attached to the primary section
+    fn test_option(course_name: &str, datetime: HashMap<String, Vec<Vec<NaiveTime>>>, instructor: &str, class_codes: &[&str]) -> Course {
+        let mut sections: Vec<Section> = class_codes.iter().map(|code| Section::parse_header(code).unwrap()).collect();
+        for (day, periods) in datetime {
+            let weekday = Weekday::parse(&day).unwrap();
+            sections[0].meetings.extend(periods.iter().map(|period| Meeting::new(weekday, period[0], period[1]).unwrap()));
+        }
+        Course::create_course_time(course_name.to_string(), instructor.to_string(), sections)
+    }
+
+    fn single_option_course(course_name: &str, day: &str, start: (u32, u32), end: (u32, u32)) -> Course {
+        test_option(
+            course_name,
+            HashMap::from(
+                [
+                    (day.to_string(), vec![vec![NaiveTime::from_hms_opt(start.0, start.1, 00).unwrap(), NaiveTime::from_hms_opt(end.0, end.1, 00).unwrap()]])
+                ]
+            ),
+            "Dr. CHAN Tai Man",
+            &[&format!("( {} ) - - LEC", start.0 * 100 + start.1)]
+        )
+    }
+
+    #[test]
+    fn test_hard_constraints_prune_options(){
+        let course_collection: DashMap<String, Vec<Course>> = DashMap::new();
+        course_collection.insert(
+            "CSCI3100".to_string(),
+            vec![
+                single_option_course("CSCI3100", "Monday", (8, 30), (10, 15)),
+                single_option_course("CSCI3100", "Monday", (13, 30), (15, 15)),
+                single_option_course("CSCI3100", "Tuesday", (18, 30), (20, 15))
+            ]
+        );
+        course_collection.insert(
+            "ELTU3502".to_string(),
+            vec![single_option_course("ELTU3502", "Monday", (10, 30), (12, 15))]
+        );
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add_constraint(EarliestStartTime(NaiveTime::from_hms_opt(9, 30, 00).unwrap()));
+        scheduler.add_constraint(ForbiddenTimeWindow {
+            days: vec![Weekday::Tuesday],
+            start: NaiveTime::from_hms_opt(18, 00, 00).unwrap(),
+            end: NaiveTime::from_hms_opt(22, 00, 00).unwrap()
+        });
+        scheduler.generate_schedule(&course_collection);
+        assert_eq!(scheduler.candidate_solutions.len(), 1);
+        assert!(scheduler.candidate_solutions[0].2.iter().all(|course| !course.meetings().iter().any(|meeting| meeting.weekday == Weekday::Tuesday)));
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add_constraint(MaxDailyHours(3.0));
+        scheduler.generate_schedule(&course_collection);
+        assert!(scheduler.candidate_solutions.iter().all(|(_, _, set)| set.iter().filter(|course| course.meetings().iter().any(|meeting| meeting.weekday == Weekday::Monday)).count() < 2));
+    }
+
+    #[test]
+    fn test_schedules_fill_around_enrolled_classes(){
+        let course_collection: DashMap<String, Vec<Course>> = DashMap::new();
+        course_collection.insert(
+            "CSCI3100".to_string(),
+            vec![
+                single_option_course("CSCI3100", "Monday", (10, 30), (12, 15)),
+                single_option_course("CSCI3100", "Monday", (13, 30), (15, 15))
+            ]
+        );
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add_commitment(single_option_course("UGFH1000", "Monday", (11, 30), (13, 15)));
+        scheduler.generate_schedule(&course_collection);
+        assert_eq!(scheduler.candidate_solutions.len(), 1);
+        // the enrolled course shows on the timetable but is not enrolled again
+        let (timetable, codes, _) = scheduler.get_next_schedule(1).unwrap();
+        assert_eq!(codes, vec![("CSCI3100".to_string(), vec!["1330".to_string()])]);
+        assert!(timetable.iter().any(|cell| cell.starts_with("UGFH1000")));
+    }
+
+    #[test]
+    fn test_walking_time_between_buildings(){
+        let campus = Arc::new(CampusMap::parse("building,LSB,Central Campus\nbuilding,UCA,United College\nwalk,Central Campus,United College,20").unwrap());
+        let mut far_option = single_option_course("CSCI3100", "Monday", (12, 30), (14, 15));
+        far_option.sections[0].meetings[0].room = "UCA 101".to_string();
+        let mut near_option = single_option_course("CSCI3100", "Monday", (14, 30), (16, 15));
+        near_option.sections[0].meetings[0].room = "LSB LT1".to_string();
+        let mut morning_class = single_option_course("ELTU3502", "Monday", (10, 30), (12, 15));
+        morning_class.sections[0].meetings[0].room = "LSB C1".to_string();
+
+        let course_collection: DashMap<String, Vec<Course>> = DashMap::new();
+        course_collection.insert("CSCI3100".to_string(), vec![far_option, near_option]);
+        course_collection.insert("ELTU3502".to_string(), vec![morning_class]);
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add_constraint(WalkingTime(campus.clone()));
+        scheduler.generate_schedule(&course_collection);
+        assert_eq!(scheduler.candidate_solutions.len(), 1);
+        assert!(scheduler.candidate_solutions[0].2.iter().any(|course| course.class_numbers() == vec![1430]));
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add_objective(MinimizeWalking(campus), 1);
+        scheduler.generate_schedule(&course_collection);
+        assert_eq!(scheduler.candidate_solutions.iter().map(|(score, _, _)| *score).collect::<Vec<i64>>(), vec![0, 20]);
+    }
+
+    #[test]
+    fn test_weighted_objectives_rank_candidates(){
+        let course_collection: DashMap<String, Vec<Course>> = DashMap::new();
+        course_collection.insert(
+            "CSCI3100".to_string(),
+            vec![
+                single_option_course("CSCI3100", "Monday", (8, 30), (10, 15)),
+                single_option_course("CSCI3100", "Monday", (14, 30), (16, 15))
+            ]
+        );
+        course_collection.insert(
+            "ELTU3502".to_string(),
+            vec![single_option_course("ELTU3502", "Monday", (10, 30), (12, 15))]
+        );
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add_objective(MinimizeGapTime, 1);
+        scheduler.add_objective(AvoidEarlyMornings(NaiveTime::from_hms_opt(9, 30, 00).unwrap()), 1);
+        scheduler.generate_schedule(&course_collection);
+        assert_eq!(scheduler.candidate_solutions.len(), 2);
+
+        // 15 minutes of gap + 60 early minutes beats a 135 minute gap
+        let (best_score, report, best_schedule) = &scheduler.candidate_solutions[0];
+        assert_eq!(*best_score, 15 + 60);
+        assert_eq!(report.contributions.iter().map(|contribution| contribution.score).collect::<Vec<i64>>(), vec![15, 60]);
+        assert!(best_schedule.iter().any(|course| course.class_numbers() == vec![830]));
+        assert_eq!(scheduler.candidate_solutions[1].0, 135);
+        assert_eq!(report.gap_minutes_per_day, vec![(Weekday::Monday, 15)]);
+        assert_eq!(report.earliest_start, NaiveTime::from_hms_opt(8, 30, 00));
+        assert_eq!(report.latest_finish, NaiveTime::from_hms_opt(12, 15, 00));
+        assert_eq!(report.free_days.len(), 5);
+        assert!(scheduler.explain_rank(0).unwrap().contains("Gap time (minutes): 15 vs 135 (better by 120)"));
+
+        let (_, _, second_report) = scheduler.get_next_schedule(1).and_then(|_| scheduler.get_next_schedule(1)).unwrap();
+        assert_eq!(second_report.total_score, 135);
+        assert!(scheduler.get_next_schedule(1).is_none());
+        assert_eq!(scheduler.get_next_schedule(0).unwrap().2.total_score, 75);
+
+        // tripling the early morning weight flips the ranking
+        let mut scheduler = Scheduler::new();
+        scheduler.add_objective(MinimizeGapTime, 1);
+        scheduler.add_objective(AvoidEarlyMornings(NaiveTime::from_hms_opt(9, 30, 00).unwrap()), 3);
+        scheduler.generate_schedule(&course_collection);
+        assert_eq!(scheduler.candidate_solutions[0].0, 135);
+    }
+
+    #[test]
+    fn test_infeasibility_diagnosis(){
+        let course_collection: DashMap<String, Vec<Course>> = DashMap::new();
+        course_collection.insert(
+            "CSCI3100".to_string(),
+            vec![
+                single_option_course("CSCI3100", "Monday", (12, 30), (14, 15)),
+                single_option_course("CSCI3100", "Monday", (11, 30), (13, 15))
+            ]
+        );
+        course_collection.insert(
+            "ELTU3502".to_string(),
+            vec![single_option_course("ELTU3502", "Monday", (12, 30), (14, 15))]
+        );
+        course_collection.insert(
+            "UGEA2163".to_string(),
+            vec![single_option_course("UGEA2163", "Friday", (9, 30), (11, 15))]
+        );
+
+        let mut scheduler = Scheduler::new();
+        scheduler.generate_schedule(&course_collection);
+        assert!(scheduler.candidate_solutions.is_empty());
+
+        let report = scheduler.diagnose_infeasibility(&course_collection).unwrap();
+        assert_eq!(report.conflicting_courses, vec!["CSCI3100".to_string(), "ELTU3502".to_string()]);
+        assert_eq!(report.conflicts, vec!["CSCI3100 LEC and ELTU3502 LEC overlap on Monday 12:30 in every option.".to_string()]);
+        assert!(report.suggestions.contains(&"drop CSCI3100".to_string()));
+        assert!(report.suggestions.contains(&"drop ELTU3502".to_string()));
+        assert!(!report.suggestions.iter().any(|suggestion| suggestion.starts_with("relax")));
+
+        course_collection.remove("ELTU3502");
+        let mut scheduler = Scheduler::new();
+        scheduler.add_constraint(EarliestStartTime(NaiveTime::from_hms_opt(10, 30, 00).unwrap()));
+        let report = scheduler.diagnose_infeasibility(&course_collection).unwrap();
+        assert_eq!(report.conflicting_courses, vec!["UGEA2163".to_string()]);
+        assert_eq!(report.conflicts, vec!["Every option of UGEA2163 breaks: No class before 10:30.".to_string()]);
+        assert_eq!(report.suggestions, vec!["drop UGEA2163".to_string(), "relax \"No class before 10:30\"".to_string()]);
+    }
+
     #[test]
     fn test_backtracking_schedulling_algorithm(){
         let course_collection_hashmap = HashMap::from(
@@ -290,9 +670,9 @@ mod tests {
                 (
                 "CSCI3180", 
                 vec![
-                    Course {   
-                        course_name: "CSCI3180".to_string(),
-                        datetime: HashMap::from(
+                    test_option(
+                        "CSCI3180",
+                        HashMap::from(
                             [
                                 ("Monday".to_string(), vec![vec![NaiveTime::from_hms_opt(14,30,00).unwrap(), NaiveTime::from_hms_opt(16,15,00).unwrap()]]),
                                 ("Tuesday".to_string(), vec![
@@ -301,128 +681,128 @@ mod tests {
                                     ]
                                 )
                             ]   
-                        ), 
-                        instructor: "Professor Lauren Marcelyn PICK".to_string(), 
-                        class_code: "( 8232 ) - - LEC".to_string(), tutorial_code: "( 8810 ) -T01 - TUT".to_string(), lab_code: "".to_string() 
-                    }, 
-                    Course { 
-                        course_name: "CSCI3180".to_string(),
-                        datetime: HashMap::from(
+                        ),
+                        "Professor Lauren Marcelyn PICK",
+                        &["( 8232 ) - - LEC", "( 8810 ) -T01 - TUT"]
+                    ), 
+                    test_option(
+                        "CSCI3180",
+                        HashMap::from(
                             [
                                 ("Tuesday".to_string(), vec![vec![NaiveTime::from_hms_opt(15,30,00).unwrap(), NaiveTime::from_hms_opt(16,15,00).unwrap()]]), 
                                 ("Thursday".to_string(), vec![vec![NaiveTime::from_hms_opt(12,30,00).unwrap(), NaiveTime::from_hms_opt(13,15,00).unwrap()]]), 
                                 ("Monday".to_string(), vec![vec![NaiveTime::from_hms_opt(14,30,00).unwrap(), NaiveTime::from_hms_opt(16,15,00).unwrap()]])
                             ]
-                        ), 
-                        instructor: "Professor Lauren Marcelyn PICK".to_string(), 
-                        class_code: "( 8232 ) - - LEC".to_string(), tutorial_code: "( 8188 ) -T03 - TUT".to_string(), lab_code: "".to_string()
-                    }, 
-                    Course { 
-                        course_name: "CSCI3180".to_string(),
-                        datetime: HashMap::from(
+                        ),
+                        "Professor Lauren Marcelyn PICK",
+                        &["( 8232 ) - - LEC", "( 8188 ) -T03 - TUT"]
+                    ), 
+                    test_option(
+                        "CSCI3180",
+                        HashMap::from(
                             [
                                 ("Wednesday".to_string(), vec![vec![NaiveTime::from_hms_opt(16,30,00).unwrap(), NaiveTime::from_hms_opt(17,15,00).unwrap()]]), 
                                 ("Monday".to_string(), vec![vec![NaiveTime::from_hms_opt(14,30,00).unwrap(), NaiveTime::from_hms_opt(16,15,00).unwrap()]]), 
                                 ("Tuesday".to_string(), vec![vec![NaiveTime::from_hms_opt(15,30,00).unwrap(), NaiveTime::from_hms_opt(16,15,00).unwrap()]])
                             ]
-                        ), 
-                        instructor: "Professor Lauren Marcelyn PICK".to_string(), 
-                        class_code: "( 8232 ) - - LEC".to_string(), tutorial_code: "( 8885 ) -T02 - TUT".to_string(), lab_code: "".to_string() 
-                    }
+                        ),
+                        "Professor Lauren Marcelyn PICK",
+                        &["( 8232 ) - - LEC", "( 8885 ) -T02 - TUT"]
+                    )
                 ]
                 ),
                 ( 
                 "CSCI3100", 
                 vec![
-                    Course { 
-                        course_name: "CSCI3100".to_string(),
-                        datetime: HashMap::from(
+                    test_option(
+                        "CSCI3100",
+                        HashMap::from(
                             [
                                 ("Tuesday".to_string(), vec![vec![NaiveTime::from_hms_opt(12,30,00).unwrap(), NaiveTime::from_hms_opt(14,15,00).unwrap()]]), 
                                 ("Monday".to_string(), vec![vec![NaiveTime::from_hms_opt(11,30,00).unwrap(), NaiveTime::from_hms_opt(12,15,00).unwrap()], vec![NaiveTime::from_hms_opt(16,30,00).unwrap(), NaiveTime::from_hms_opt(17,15,00).unwrap()]])
                             ]
-                        ), 
-                        instructor: "Dr. LAM Tak Kei".to_string(), 
-                        class_code: "( 8249 ) - - LEC".to_string(), tutorial_code: "( 8853 ) -T01 - TUT".to_string(), lab_code: "".to_string()
-                    }, 
-                    Course { 
-                        course_name: "CSCI3100".to_string(),
-                        datetime: HashMap::from(
+                        ),
+                        "Dr. LAM Tak Kei",
+                        &["( 8249 ) - - LEC", "( 8853 ) -T01 - TUT"]
+                    ), 
+                    test_option(
+                        "CSCI3100",
+                        HashMap::from(
                             [
                                 ("Tuesday".to_string(), vec![vec![NaiveTime::from_hms_opt(12,30,00).unwrap(), NaiveTime::from_hms_opt(14,15,00).unwrap()]]), 
                                 ("Monday".to_string(), vec![vec![NaiveTime::from_hms_opt(11,30,00).unwrap(), NaiveTime::from_hms_opt(12,15,00).unwrap()]]), 
                                 ("Wednesday".to_string(), vec![vec![NaiveTime::from_hms_opt(17,30,00).unwrap(), NaiveTime::from_hms_opt(18,15,00).unwrap()]])
                             ]
-                        ), 
-                        instructor: "Dr. LAM Tak Kei".to_string(), 
-                        class_code: "( 8249 ) - - LEC".to_string(), tutorial_code: "( 8208 ) -T03 - TUT".to_string(), lab_code: "".to_string() 
-                    }, 
-                    Course { 
-                        course_name: "CSCI3100".to_string(),
-                        datetime: HashMap::from(
+                        ),
+                        "Dr. LAM Tak Kei",
+                        &["( 8249 ) - - LEC", "( 8208 ) -T03 - TUT"]
+                    ), 
+                    test_option(
+                        "CSCI3100",
+                        HashMap::from(
                             [
                                 ("Tuesday".to_string(), vec![vec![NaiveTime::from_hms_opt(12,30,00).unwrap(), NaiveTime::from_hms_opt(14,15,00).unwrap()]]), 
                                 ("Monday".to_string(), vec![vec![NaiveTime::from_hms_opt(11,30,00).unwrap(), NaiveTime::from_hms_opt(12,15,00).unwrap()], vec![NaiveTime::from_hms_opt(17,30,00).unwrap(), NaiveTime::from_hms_opt(18,15,00).unwrap()]])
                             ]
-                        ), 
-                        instructor: "Dr. LAM Tak Kei".to_string(), 
-                        class_code: "( 8249 ) - - LEC".to_string(), tutorial_code: "( 8034 ) -T02 - TUT".to_string(), lab_code: "".to_string() 
-                    }
+                        ),
+                        "Dr. LAM Tak Kei",
+                        &["( 8249 ) - - LEC", "( 8034 ) -T02 - TUT"]
+                    )
                 ]
                 ), 
                 (
                 "UGEA2163", 
                 vec![
-                    Course { 
-                        course_name: "UGEA2163".to_string(),
-                        datetime: HashMap::from(
+                    test_option(
+                        "UGEA2163",
+                        HashMap::from(
                             [
-                                ("Friday".to_string(), vec![vec![NaiveTime::from_hms_opt(09,30,00).unwrap(), NaiveTime::from_hms_opt(11,15,00).unwrap()]])
+                                ("Friday".to_string(), vec![vec![NaiveTime::from_hms_opt(9,30,00).unwrap(), NaiveTime::from_hms_opt(11,15,00).unwrap()]])
                             ]
                         ),
-                        instructor: "Dr. LAU Po Hei".to_string(), 
-                        class_code: "( 8255 ) - - LEC".to_string(), tutorial_code: "".to_string(), lab_code: "".to_string() 
-                    }
+                        "Dr. LAU Po Hei",
+                        &["( 8255 ) - - LEC"]
+                    )
                 ]
                 ), 
                 ( 
                 "ELTU3502", 
                 vec![
-                    Course { 
-                        course_name: "ELTU3502".to_string(),
-                        datetime: HashMap::from(
+                    test_option(
+                        "ELTU3502",
+                        HashMap::from(
                             [
                                 ("Monday".to_string(), vec![vec![NaiveTime::from_hms_opt(12,30,00).unwrap(), NaiveTime::from_hms_opt(14,15,00).unwrap()]])
                             ]
                         ),
-                        instructor: "Ms. LEUNG Kit Chi Ella".to_string(), 
-                        class_code: "( 4980 ) BC01 - CLW".to_string(), tutorial_code: "".to_string(), lab_code: "".to_string() 
-                    }, 
-                    Course { 
-                        course_name: "ELTU3502".to_string(),
-                        datetime: HashMap::from(
+                        "Ms. LEUNG Kit Chi Ella",
+                        &["( 4980 ) BC01 - CLW"]
+                    ), 
+                    test_option(
+                        "ELTU3502",
+                        HashMap::from(
                             [
                                 ("Thursday".to_string(), vec![vec![NaiveTime::from_hms_opt(10,30,00).unwrap(), NaiveTime::from_hms_opt(12,15,00).unwrap()]])
                             ]
-                        ), 
-                        instructor: "Ms. LEUNG Kit Chi Ella".to_string(), 
-                        class_code: "( 9663 ) CC01 - CLW".to_string(), tutorial_code: "".to_string(), lab_code: "".to_string() 
-                    }
+                        ),
+                        "Ms. LEUNG Kit Chi Ella",
+                        &["( 9663 ) CC01 - CLW"]
+                    )
                 ]
                 ),
                 (
                     "CSCI3250", 
                     vec![
-                        Course { 
-                            course_name: "CSCI3250".to_string(),
-                            datetime: HashMap::from(
+                        test_option(
+                        "CSCI3250",
+                        HashMap::from(
                                 [
                                     ("Thursday".to_string(), vec![vec![NaiveTime::from_hms_opt(13,30,00).unwrap(), NaiveTime::from_hms_opt(15,15,00).unwrap()]])
                                 ]
-                            ), 
-                            instructor: "Dr. Umair Mujtaba QURESHI".to_string(), 
-                            class_code: "( 9085 ) - - LEC".to_string(), tutorial_code: "".to_string(), lab_code: "".to_string() 
-                        }
+                            ),
+                        "Dr. Umair Mujtaba QURESHI",
+                        &["( 9085 ) - - LEC"]
+                    )
                     ]
                 )
         ]
@@ -433,8 +813,11 @@ mod tests {
     }    
 
     let mut scheduler = Scheduler::new();
-    scheduler.generate_schedule(&(*course_collection).clone(), "Thursday".to_string());
-    scheduler.get_schedule_with_best_fitness_score();
-    assert!(scheduler.candidate_solutions.len() > 0);
+    scheduler.add_objective(MinimizeGapTime, 1);
+    scheduler.add_objective(PreferDayOff(Weekday::Thursday), 100);
+    scheduler.generate_schedule(&(*course_collection).clone());
+    assert!(scheduler.get_schedule_with_best_fitness_score().is_some());
+    assert!(!scheduler.candidate_solutions.is_empty());
+    assert!(scheduler.get_next_schedule(1).is_some());
     }
 }
@@ -0,0 +1,19 @@
+// Explains why the scheduler could not produce any conflict-free schedule
+#[derive(Debug, Clone, PartialEq)]
+pub struct InfeasibilityReport {
+    // a minimal set of courses that cannot be scheduled together, dropping any one of them removes this conflict
+    pub conflicting_courses: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub suggestions: Vec<String>
+}
+
+impl InfeasibilityReport {
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!("No schedule can fit {} together.", self.conflicting_courses.join(", "))];
+        lines.extend(self.conflicts.iter().cloned());
+        if !self.suggestions.is_empty() {
+            lines.push(format!("Suggestions: {}", self.suggestions.join("; ")));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use chrono::{Duration, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday
+    ];
+
+    // days CUSIS schedules classes on, these are the columns of the timetable
+    pub const SCHOOL_DAYS: [Weekday; 6] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weekday::Monday => "Monday",
+            Weekday::Tuesday => "Tuesday",
+            Weekday::Wednesday => "Wednesday",
+            Weekday::Thursday => "Thursday",
+            Weekday::Friday => "Friday",
+            Weekday::Saturday => "Saturday",
+            Weekday::Sunday => "Sunday"
+        }
+    }
+
+    pub fn short_name(&self) -> &'static str {
+        &self.name()[..3]
+    }
+
+    pub fn parse(text: &str) -> Option<Weekday> {
+        let text = text.trim();
+        Weekday::ALL.into_iter().find(|day| day.name().eq_ignore_ascii_case(text) || day.short_name().eq_ignore_ascii_case(text))
+    }
+
+    // every full day name mentioned in a line of scraped text
+    pub fn find_all_in(line: &str) -> Vec<Weekday> {
+        Weekday::ALL.into_iter().filter(|day| line.contains(day.name())).collect()
+    }
+}
+
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Meeting {
+    pub weekday: Weekday,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    // venue such as "Lady Shaw Bldg LT1", empty when unknown
+    pub room: String,
+    // first and last date the meeting runs, None when CUSIS did not list them
+    pub date_range: Option<(NaiveDate, NaiveDate)>
+}
+
+impl Meeting {
+    // returns None for an empty or reversed time range
+    pub fn new(weekday: Weekday, start: NaiveTime, end: NaiveTime) -> Option<Meeting> {
+        if start >= end {
+            return None;
+        }
+        Some(Meeting {
+            weekday,
+            start,
+            end,
+            room: String::new(),
+            date_range: None
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    // meetings on the same weekday clash when their times overlap and their date ranges (if known) intersect
+    pub fn overlaps(&self, other: &Meeting) -> bool {
+        if self.weekday != other.weekday || self.start >= other.end || self.end <= other.start {
+            return false;
+        }
+        match (self.date_range, other.date_range) {
+            (Some((first_from, first_to)), Some((second_from, second_to))) => first_from <= second_to && second_from <= first_to,
+            _ => true
+        }
+    }
+
+    // the end is excluded, a class ending at 10:15 does not hold 10:15
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        self.start <= time && time < self.end
+    }
+
+    pub fn contains_meeting(&self, other: &Meeting) -> bool {
+        self.weekday == other.weekday && self.start <= other.start && other.end <= self.end
+    }
+
+    // "14:30 - 16:15"
+    pub fn time_range(&self) -> String {
+        format!("{} - {}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+// "Monday 14:30 - 16:15 @ Lady Shaw Bldg LT1", the venue is left out when CUSIS did not list one
+impl fmt::Display for Meeting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.weekday, self.time_range())?;
+        if !self.room.is_empty() {
+            write!(f, " @ {}", self.room)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meeting(weekday: Weekday, start: (u32, u32), end: (u32, u32)) -> Meeting {
+        Meeting::new(weekday, NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(), NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_meeting_overlap_and_containment() {
+        let lecture = meeting(Weekday::Monday, (12, 30), (14, 15));
+        assert!(lecture.overlaps(&meeting(Weekday::Monday, (13, 30), (14, 15))));
+        assert!(!lecture.overlaps(&meeting(Weekday::Monday, (14, 15), (15, 15))));
+        assert!(!lecture.overlaps(&meeting(Weekday::Tuesday, (12, 30), (14, 15))));
+        assert!(lecture.contains_meeting(&meeting(Weekday::Monday, (12, 30), (13, 15))));
+        assert!(lecture.contains(NaiveTime::from_hms_opt(12, 30, 0).unwrap()));
+        assert!(!lecture.contains(NaiveTime::from_hms_opt(14, 15, 0).unwrap()));
+        assert_eq!(lecture.duration().num_minutes(), 105);
+
+        let mut first_half = lecture.clone();
+        first_half.date_range = Some((NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(), NaiveDate::from_ymd_opt(2025, 10, 15).unwrap()));
+        let mut second_half = lecture.clone();
+        second_half.date_range = Some((NaiveDate::from_ymd_opt(2025, 10, 16).unwrap(), NaiveDate::from_ymd_opt(2025, 12, 1).unwrap()));
+        assert!(!first_half.overlaps(&second_half));
+        assert!(first_half.overlaps(&lecture));
+    }
+
+    #[test]
+    fn test_malformed_time_range_is_rejected() {
+        let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        assert!(Meeting::new(Weekday::Sunday, noon, noon).is_none());
+        assert_eq!(Weekday::parse("sun"), Some(Weekday::Sunday));
+        assert_eq!(Weekday::find_all_in("Monday Wednesday"), vec![Weekday::Monday, Weekday::Wednesday]);
+    }
+}
@@ -1,2 +1,9 @@
 pub mod course_scheduler;
-pub mod course_manager;
\ No newline at end of file
+pub mod course_manager;
+pub mod constraint;
+pub mod objective;
+pub mod score_report;
+pub mod infeasibility;
+pub mod meeting;
+pub mod campus;
+pub mod catalog;
\ No newline at end of file
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use chrono::NaiveTime;
+use super::campus::CampusMap;
+use super::meeting::{Meeting, Weekday};
+
+// A soft preference used to rank complete schedules.
+// `penalty` receives the schedule's meetings sorted by start time and keyed by weekday, a lower penalty is better.
+pub trait Objective: Debug + Send + Sync {
+    fn name(&self) -> String;
+    fn penalty(&self, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> i64;
+}
+
+// The share of a candidate's score that comes from one objective
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectiveContribution {
+    pub name: String,
+    pub weight: i64,
+    pub penalty: i64,
+    pub score: i64
+}
+
+#[derive(Debug, Clone)]
+pub struct MinimizeGapTime;
+
+impl Objective for MinimizeGapTime {
+    fn name(&self) -> String {
+        "Gap time (minutes)".to_string()
+    }
+
+    fn penalty(&self, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> i64 {
+        time_slot.values()
+            .map(|day| {
+                day.windows(2)
+                    .map(|pair| (pair[1].start - pair[0].end).num_minutes().max(0))
+                    .sum::<i64>()
+            })
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaximizeFreeDays;
+
+impl Objective for MaximizeFreeDays {
+    fn name(&self) -> String {
+        "Days on campus".to_string()
+    }
+
+    fn penalty(&self, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> i64 {
+        time_slot.values().filter(|day| !day.is_empty()).count() as i64
+    }
+}
+
+// Keeps one particular weekday free, the penalty is the number of classes left on that day
+#[derive(Debug, Clone)]
+pub struct PreferDayOff(pub Weekday);
+
+impl Objective for PreferDayOff {
+    fn name(&self) -> String {
+        format!("Classes on {}", self.0)
+    }
+
+    fn penalty(&self, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> i64 {
+        time_slot.get(&self.0).map(|day| day.len() as i64).unwrap_or(0)
+    }
+}
+
+// Penalizes every minute of class that starts before the given time
+#[derive(Debug, Clone)]
+pub struct AvoidEarlyMornings(pub NaiveTime);
+
+impl Objective for AvoidEarlyMornings {
+    fn name(&self) -> String {
+        format!("Minutes of class before {}", self.0.format("%H:%M"))
+    }
+
+    fn penalty(&self, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> i64 {
+        time_slot.values()
+            .flatten()
+            .filter(|meeting| meeting.start < self.0)
+            .map(|meeting| (meeting.end.min(self.0) - meeting.start).num_minutes())
+            .sum()
+    }
+}
+
+// A day with a single class means a whole trip to campus for one lesson, so such days are penalized
+#[derive(Debug, Clone)]
+pub struct PreferCompactDays;
+
+impl Objective for PreferCompactDays {
+    fn name(&self) -> String {
+        "Days with a single class".to_string()
+    }
+
+    fn penalty(&self, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> i64 {
+        time_slot.values().filter(|day| day.len() == 1).count() as i64
+    }
+}
+
+// Asks for a free period of at least `minimum_minutes` between `start` and `end` on every teaching day,
+// the penalty is the total shortfall in minutes
+#[derive(Debug, Clone)]
+pub struct ProtectLunchBreak {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub minimum_minutes: i64
+}
+
+impl Objective for ProtectLunchBreak {
+    fn name(&self) -> String {
+        format!("Lunch break shortfall between {} and {} (minutes)", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+
+    fn penalty(&self, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> i64 {
+        time_slot.values()
+            .filter(|day| !day.is_empty())
+            .map(|day| {
+                let mut longest_break = 0;
+                let mut free_from = self.start;
+                for meeting in day {
+                    if meeting.end <= self.start || meeting.start >= self.end {
+                        continue;
+                    }
+                    longest_break = longest_break.max((meeting.start - free_from).num_minutes());
+                    free_from = free_from.max(meeting.end);
+                }
+                if free_from < self.end {
+                    longest_break = longest_break.max((self.end - free_from).num_minutes());
+                }
+                (self.minimum_minutes - longest_break).max(0)
+            })
+            .sum()
+    }
+}
+
+// Penalizes every minute spent walking between consecutive classes of a day
+#[derive(Debug, Clone)]
+pub struct MinimizeWalking(pub Arc<CampusMap>);
+
+impl Objective for MinimizeWalking {
+    fn name(&self) -> String {
+        "Walking between classes (minutes)".to_string()
+    }
+
+    fn penalty(&self, time_slot: &HashMap<Weekday, Vec<Meeting>>) -> i64 {
+        time_slot.values()
+            .flat_map(|day| day.windows(2))
+            .map(|pair| self.0.walking_minutes(&pair[0], &pair[1]))
+            .sum()
+    }
+}
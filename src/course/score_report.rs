@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use chrono::NaiveTime;
+use super::meeting::{Meeting, Weekday};
+use super::objective::ObjectiveContribution;
+
+// A structured explanation of how a candidate schedule was scored
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreReport {
+    pub total_score: i64,
+    pub contributions: Vec<ObjectiveContribution>,
+    pub gap_minutes_per_day: Vec<(Weekday, i64)>,
+    pub free_days: Vec<Weekday>,
+    pub earliest_start: Option<NaiveTime>,
+    pub latest_finish: Option<NaiveTime>,
+    pub preferences_met: Vec<String>,
+    pub preferences_missed: Vec<String>
+}
+
+impl ScoreReport {
+    // `time_slot` is the candidate's meetings sorted by start time and keyed by weekday
+    pub fn new(time_slot: &HashMap<Weekday, Vec<Meeting>>, contributions: Vec<ObjectiveContribution>) -> ScoreReport {
+        let mut gap_minutes_per_day = vec![];
+        let mut free_days = vec![];
+        let mut earliest_start: Option<NaiveTime> = None;
+        let mut latest_finish: Option<NaiveTime> = None;
+
+        for day in Weekday::SCHOOL_DAYS {
+            let meetings = time_slot.get(&day).map(Vec::as_slice).unwrap_or_default();
+            if meetings.is_empty() {
+                free_days.push(day);
+                continue;
+            }
+            let gap_minutes = meetings.windows(2)
+                .map(|pair| (pair[1].start - pair[0].end).num_minutes().max(0))
+                .sum();
+            gap_minutes_per_day.push((day, gap_minutes));
+            for meeting in meetings {
+                earliest_start = Some(earliest_start.map_or(meeting.start, |time| time.min(meeting.start)));
+                latest_finish = Some(latest_finish.map_or(meeting.end, |time| time.max(meeting.end)));
+            }
+        }
+
+        // objectives switched off with a zero weight are neither met nor missed
+        let (met, missed): (Vec<&ObjectiveContribution>, Vec<&ObjectiveContribution>) = contributions.iter()
+            .filter(|contribution| contribution.weight != 0)
+            .partition(|contribution| contribution.penalty == 0);
+
+        ScoreReport {
+            total_score: contributions.iter().map(|contribution| contribution.score).sum(),
+            gap_minutes_per_day,
+            free_days,
+            earliest_start,
+            latest_finish,
+            preferences_met: met.into_iter().map(|contribution| contribution.name.clone()).collect(),
+            preferences_missed: missed.into_iter().map(|contribution| format!("{} ({})", contribution.name, contribution.penalty)).collect(),
+            contributions
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!("Score: {} (lower is better)", self.total_score)];
+        for contribution in &self.contributions {
+            lines.push(format!("  {}: {} x {} = {}", contribution.name, contribution.penalty, contribution.weight, contribution.score));
+        }
+        let gaps: Vec<String> = self.gap_minutes_per_day.iter()
+            .map(|(day, minutes)| format!("{} {}m", day.short_name(), minutes))
+            .collect();
+        lines.push(format!("Gaps: {}", if gaps.is_empty() { "-".to_string() } else { gaps.join(", ") }));
+        lines.push(format!("Free days: {}", if self.free_days.is_empty() { "-".to_string() } else { self.free_days.iter().map(|day| day.name()).collect::<Vec<_>>().join(", ") }));
+        if let (Some(start), Some(finish)) = (self.earliest_start, self.latest_finish) {
+            lines.push(format!("Earliest start: {}, latest finish: {}", start.format("%H:%M"), finish.format("%H:%M")));
+        }
+        if !self.preferences_met.is_empty() {
+            lines.push(format!("Met: {}", self.preferences_met.join("; ")));
+        }
+        if !self.preferences_missed.is_empty() {
+            lines.push(format!("Missed: {}", self.preferences_missed.join("; ")));
+        }
+        lines.join("\n")
+    }
+
+    // explains which objectives made this report rank ahead of `other`
+    pub fn explain_against(&self, other: &ScoreReport) -> String {
+        let mut reasons: Vec<(i64, String)> = self.contributions.iter()
+            .filter_map(|contribution| {
+                let other_score = other.contributions.iter()
+                    .find(|other_contribution| other_contribution.name == contribution.name)
+                    .map(|other_contribution| other_contribution.score)
+                    .unwrap_or(0);
+                let difference = other_score - contribution.score;
+                if difference == 0 {
+                    return None;
+                }
+                let verdict = if difference > 0 { "better" } else { "worse" };
+                Some((difference, format!("{}: {} vs {} ({} by {})", contribution.name, contribution.score, other_score, verdict, difference.abs())))
+            })
+            .collect();
+        if reasons.is_empty() {
+            return "Tied on every objective".to_string();
+        }
+        reasons.sort_by_key(|reason| std::cmp::Reverse(reason.0));
+        reasons.into_iter().map(|(_, reason)| reason).collect::<Vec<String>>().join("\n")
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+use thirtyfour::error::WebDriverError;
+
+// Everything that can go wrong while talking to CUSIS, split by what the user can do about it
+#[derive(Debug)]
+pub enum CusisError {
+    BadCredentials,
+    DuoTimeout,
+    DuoDenied,
+    TermNotFound(String),
+    CourseNotOffered(String),
+    NoOpenSections(String),
+    // the element CUSIS no longer renders where it used to
+    PageLayoutChanged(String),
+    DriverUnavailable(String),
+    SessionExpired,
+    // an import file or saved page that cannot be read
+    InvalidCourseData(String),
+    // CUSIS refused the submission or added other classes than the ones picked
+    EnrollmentRejected(String),
+    WebDriver(WebDriverError)
+}
+
+pub type CusisResult<T> = Result<T, CusisError>;
+
+impl fmt::Display for CusisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CusisError::BadCredentials => write!(f, "Incorrect user name or password"),
+            CusisError::DuoTimeout => write!(f, "Duo was not approved in time"),
+            CusisError::DuoDenied => write!(f, "The Duo request was denied"),
+            CusisError::TermNotFound(term) => write!(f, "Term {} cannot be found on CUSIS", term),
+            CusisError::CourseNotOffered(course) => write!(f, "{} is not offered in the selected term", course),
+            CusisError::NoOpenSections(course) => write!(f, "{} does not have any open class", course),
+            CusisError::PageLayoutChanged(element) => write!(f, "CUSIS page layout changed, cannot find {}", element),
+            CusisError::DriverUnavailable(reason) => write!(f, "The browser driver is unavailable: {}", reason),
+            CusisError::SessionExpired => write!(f, "The CUSIS session has expired"),
+            CusisError::InvalidCourseData(reason) => write!(f, "Invalid course data: {}", reason),
+            CusisError::EnrollmentRejected(reason) => write!(f, "CUSIS did not take the classes: {}", reason),
+            CusisError::WebDriver(e) => write!(f, "WebDriverError: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for CusisError {}
+
+impl From<WebDriverError> for CusisError {
+    fn from(e: WebDriverError) -> CusisError {
+        CusisError::WebDriver(e)
+    }
+}
@@ -1,34 +1,46 @@
-use fancy_regex::Regex;
-use slint::spawn_local;
 use slint::{PlatformError, SharedString, Weak, VecModel, ModelRc};
-use thirtyfour::error::WebDriverErrorInner;
-use std::f32::consts::LOG10_2;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::sync::mpsc::channel;
-use std::sync::atomic::{AtomicU64, Ordering};
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 use thirtyfour::{prelude::*};
 use dashmap::DashMap;
-use webdriver::{process_manager, scrape, util};
+use webdriver::{cart, class_schedule, process_manager, screenshot, util};
+use webdriver::mock_server::MockCusisServer;
+use webdriver::session::{Session, SessionEvent};
+use webdriver::duo::{self, DuoMethod};
+use webdriver::cookie_store::CookieStore;
 mod course;
 mod webdriver;
 mod cli;
+mod parser;
+mod config;
+mod source;
+mod error;
 use tokio::time::Duration;
 use std::rc::Rc;
-use tokio::time;
 use course::course_scheduler::Scheduler;
-use course::course_manager::Course;
-use scrape::{*, CourseSearchTask};
-use async_compat::{Compat, CompatExt};
+use course::course_manager::{Course, EnrollmentMode};
+use course::campus::CampusMap;
+use course::catalog::{CourseCatalog, FreshnessPolicy};
+use course::constraint::{EarliestStartTime, ForbiddenDays, ForbiddenTimeWindow, LatestEndTime, MaxDailyHours, SeatAvailability, WalkingTime};
+use course::objective::{AvoidEarlyMornings, MaximizeFreeDays, MinimizeGapTime, MinimizeWalking, PreferCompactDays, PreferDayOff, ProtectLunchBreak};
+use course::meeting::Weekday;
+use chrono::NaiveTime;
+use error::{CusisError, CusisResult};
+use source::SourceChoice;
 use util::*;
 use cli::animation::Spinner;
 
 const CUSIS_LINK: &str = "https://cusis.cuhk.edu.hk/psp/CSPRD/?cmd=login&languageCd=ENG&";
 const CUSIS_COURSE_SEARCH_LINK: &str = "https://cusis.cuhk.edu.hk/psc/CSPRD_4/EMPLOYEE/SA/c/SSR_STUDENT_FL.SSR_CLSRCH_MAIN_FL.GBL?Page=SSR_CLSRCH_MAIN_FL";
-const GECKODRIVER_PORT: &str = "4444";
-const VALID_SCL_DAYS: [&str; 6] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+const CUSIS_SHOPPING_CART_LINK: &str = "https://cusis.cuhk.edu.hk/psc/CSPRD_4/EMPLOYEE/SA/c/SSR_STUDENT_FL.SSR_SHOP_CART_FL.GBL";
+const CUSIS_CLASS_SCHEDULE_LINK: &str = "https://cusis.cuhk.edu.hk/psc/CSPRD_4/EMPLOYEE/SA/c/SA_LEARNER_SERVICES.SSR_SSENRL_LIST.GBL";
+const WEBDRIVER_PORT: &str = "4444";
+
+// term name to its button on the class search page and the button's id, filled in once registration opens
+type TermTable = Arc<std::sync::Mutex<Option<HashMap<String, (WebElement, String)>>>>;
 
 slint::include_modules!();
 
@@ -42,36 +54,100 @@ fn main() -> Result<(), PlatformError> {
             .map_err(|e| PlatformError::from(format!("Failed to create Tokio runtime: {}", e)))?
     );
 
-    // Start Geckodriver
-    let _child = process_manager::GeckodriverGuard(
-        process_manager::spawn_process(cfg!(windows), &["./geckodriver.exe", "--port", GECKODRIVER_PORT])
-            .map_err(|e| PlatformError::Other(format!("Failed to start Geckodriver: {}", e)))?,
+    // `--plan` prints a schedule on the command line instead of opening the app, see cli::plan
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(plan_args) = cli::plan::PlanArgs::parse(&args) {
+        let result = match plan_args {
+            Ok(plan_args) => rt.block_on(cli::plan::run(plan_args)).map_err(|e| e.to_string()),
+            Err(e) => Err(format!("{}\n{}", e, cli::plan::USAGE))
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Serve the mock portal when asked to, every CUSIS link then points at it
+    let _mock_server = match config::mock_server_addr() {
+        Some(addr) => {
+            let server = rt.block_on(MockCusisServer::start(&addr))
+                .map_err(|e| PlatformError::Other(format!("Failed to start the mock CUSIS server: {}", e)))?;
+            println!("Serving the mock CUSIS portal on {}", server.addr);
+            Some(server)
+        }
+        None => None
+    };
+
+    // Start geckodriver or chromedriver
+    let browser = config::browser_backend();
+    let _child = process_manager::DriverGuard(
+        process_manager::spawn_driver(browser, WEBDRIVER_PORT)
+            .map_err(|e| PlatformError::Other(format!("Failed to start {}: {}", browser.driver_name(), e)))?,
     );
 
     // Initialize WebDriver
     let driver = rt.block_on(async {
-        init_driver()
+        init_driver(browser)
             .await
-            .map_err(|e| PlatformError::Other(format!("WebDriverError: {}", e)))
+            .map_err(|e| PlatformError::Other(e.to_string()))
     })?;
     let driver_reg = Arc::new(Mutex::new(driver.clone()));
     let driver_arc = Arc::new(driver);
-    let terms_hashmap: Arc<std::sync::Mutex<Option<HashMap<String, (WebElement, String)>>>> = Arc::new(std::sync::Mutex::new(None));
+    let terms_hashmap: TermTable = Arc::new(std::sync::Mutex::new(None));
     let terms_hashmap_clone = terms_hashmap.clone();
     let term_hashmap_use_clone = terms_hashmap.clone();
-    // Navigate to CUSIS
-    rt.block_on(async {
-        goto_cusis(&driver_arc)
-            .await
-            .map_err(|e| PlatformError::Other(format!("Cannot connect to CUSIS: {}", e)))
-    })?;
+    // Navigate to CUSIS, without it the app can still plan from the course catalog
+    let cusis_reachable = match rt.block_on(goto_cusis(&driver_arc)) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Cannot connect to CUSIS: {}", e);
+            false
+        }
+    };
 
     // Create the UI instance
     let app = App::new()?;
     app.window().set_maximized(true);
+    if !cusis_reachable {
+        app.set_message("CUSIS cannot be reached, you can still plan offline with the courses cached before".into());
+    }
+    // an import file or saved pages named in the environment are chosen already
+    if let Some(choice) = SourceChoice::from_env() {
+        app.set_course_source(choice.label().into());
+        app.set_source_path(choice.path().into());
+    }
+
+    // Signs back in when CUSIS times the session out mid-search, the DUO step shows on the 2FA page again
+    let session = Arc::new(Session::new(CookieStore::from_env(), {
+        let session_ui_weak = app.as_weak();
+        move |event| {
+            let session_ui_weak = session_ui_weak.clone();
+            let _ = slint::invoke_from_event_loop(move || {
+                if let Some(ui) = session_ui_weak.upgrade() {
+                    match event {
+                        SessionEvent::Expired => {
+                            ui.set_current_page(Pages::TwoFactorAuth);
+                            ui.set_duo_waiting(true);
+                            ui.set_duo_options(ModelRc::new(VecModel::from(Vec::<SharedString>::new())));
+                            ui.set_duo_status("Your CUSIS session expired, signing in again...".into());
+                        }
+                        SessionEvent::DuoChoice(labels) => {
+                            let labels: Vec<SharedString> = labels.iter().map(|label| label.as_str().into()).collect();
+                            ui.set_duo_options(ModelRc::new(VecModel::from(labels)));
+                            ui.set_duo_waiting(false);
+                            ui.set_duo_status("Your CUSIS session expired, choose how to confirm the new login".into());
+                        }
+                        SessionEvent::DuoStatus(status) => ui.set_duo_status(status.into()),
+                        SessionEvent::Restored => ui.set_current_page(Pages::Registration)
+                    }
+                }
+            });
+        }
+    }));
 
     // Create a channel for login results
-    let (tx, mut rx) = unbounded_channel::<String>();
+    let (tx, mut rx) = unbounded_channel::<CusisResult<()>>();
     let driver_clone = driver_arc.clone();
     let login_ui_weak = app.as_weak();
     let driver_cleanup = driver_arc.clone();
@@ -81,16 +157,17 @@ fn main() -> Result<(), PlatformError> {
     //let rt_sch_course_clone = rt.clone();
     let rt_course_clone = rt.clone();
     let reg_ui_weak = app.as_weak();
-    let reg_ui_weak_clone = app.as_weak();
     // Handle login
     app.on_handle_login({
-        let tx: tokio::sync::mpsc::UnboundedSender<String> = tx.clone();
+        let tx: tokio::sync::mpsc::UnboundedSender<CusisResult<()>> = tx.clone();
         let driver = driver_arc.clone();
         let login_ui_weak = login_ui_weak.clone();
+        let session = session.clone();
 
         move |student_id, password| {
             let tx = tx.clone();
             let driver = driver.clone();
+            let session = session.clone();
             let login_ui_weak = login_ui_weak.clone();
             let student_id = format!("{}@link.cuhk.edu.hk", student_id);
             let password = password.to_string();
@@ -108,13 +185,82 @@ fn main() -> Result<(), PlatformError> {
 
             // Spawn login task in Tokio runtime to se
             rt_clone.spawn(async move {
-                match login(&driver, student_id, password).await {
-                    Ok(_) => {
-                        tx.send("Login successfully".to_string()).unwrap();
+                // a failed Duo attempt leaves the browser on the Duo page
+                if driver.title().await.map_or(true, |title| title != "Sign In") {
+                    if let Err(e) = goto_cusis(&driver).await {
+                        tx.send(Err(e)).unwrap();
+                        return;
                     }
-                    Err(e) => {
-                        tx.send(format!("Login failed: {}", e)).unwrap();
+                }
+                let result = screenshot::on_error(&driver, "login", login(&driver, student_id.clone(), password.clone()).await).await;
+                if result.is_ok() {
+                    session.remember_credentials(student_id, password);
+                }
+                tx.send(result).unwrap();
+            });
+        }
+    });
+
+    // a session kept from the last run skips the login page and DUO altogether
+    if cusis_reachable && session.has_saved_cookies() {
+        app.set_is_loading(true);
+        app.set_message("Restoring your last session...".into());
+        rt.spawn({
+            let driver = driver_arc.clone();
+            let session = session.clone();
+            let terms_hashmap = terms_hashmap.clone();
+            let ui_weak = app.as_weak();
+            async move {
+                let resumed = match session.resume(&driver).await {
+                    Ok(true) => open_registration(&driver, &terms_hashmap, ui_weak.clone()).await,
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e)
+                };
+                if let Err(e) = resumed {
+                    eprintln!("Failed to restore the saved session: {}", e);
+                }
+                slint::invoke_from_event_loop(move || {
+                    if let Some(ui) = ui_weak.upgrade() {
+                        ui.set_is_loading(false);
+                        ui.set_message("".into());
+                    }
+                }).unwrap();
+            }
+        });
+    }
+
+    // Sends the Duo option picked on the 2FA page and waits for it to be confirmed
+    app.on_duo_request({
+        let tx = tx.clone();
+        let driver = driver_arc.clone();
+        let session = session.clone();
+        let terms_hashmap = terms_hashmap.clone();
+        let ui_weak = app.as_weak();
+        let rt = rt.clone();
+
+        move |index, passcode| {
+            let tx = tx.clone();
+            let driver = driver.clone();
+            let session = session.clone();
+            let terms_hashmap = terms_hashmap.clone();
+            let ui_weak = ui_weak.clone();
+            let passcode = passcode.to_string();
+            if let Some(ui) = ui_weak.upgrade() {
+                ui.set_duo_waiting(true);
+                ui.set_duo_status("Sending the request to Duo...".into());
+            }
+            // a session being restored mid-search waits for this choice itself
+            if session.choose_duo(index.max(0) as usize, passcode.clone()) {
+                return;
+            }
+            rt.spawn(async move {
+                let result = duo::request(&driver, index.max(0) as usize, &passcode).await;
+                match screenshot::on_error(&driver, "duo-request", result).await {
+                    Ok(method) => {
+                        set_duo_state(&ui_weak, duo::waiting_status(method).to_string(), true);
+                        complete_duo(&driver, &session, &terms_hashmap, ui_weak, &tx).await;
                     }
+                    Err(e) => tx.send(Err(e)).unwrap()
                 }
             });
         }
@@ -122,8 +268,10 @@ fn main() -> Result<(), PlatformError> {
 
     let rt_clone = rt.clone();
     // Handle login result
+    let login_session = session.clone();
     rt_clone.spawn(async move {
         while let Some(result) = rx.recv().await {
+            let session = login_session.clone();
             let login_ui_weak = login_ui_weak.clone();
             let driver = driver_arc.clone();
             let tx = tx.clone();
@@ -132,84 +280,120 @@ fn main() -> Result<(), PlatformError> {
             slint::invoke_from_event_loop(move || {
                 if let Some(ui) = login_ui_weak.upgrade() {
                     ui.set_is_loading(false);
-                    match result.as_str() {
-                        "Login successfully" => {
+                    match result {
+                        Ok(()) => {
                             let driver = driver.clone();
                             let tx = tx.clone();
                             let login_ui_weak = login_ui_weak.clone();
                             ui.set_message("Login successfully, please confirm your login session in DUO".into());
-                            // Spawn DUO auth task
+                            // the browser is usually hidden, so the DUO step is followed on the app's own page
+                            ui.set_current_page(Pages::TwoFactorAuth);
+                            ui.set_duo_waiting(false);
+                            ui.set_duo_options(ModelRc::new(VecModel::from(Vec::<SharedString>::new())));
+                            // Spawn DUO auth task, the user picks the device or method on the 2FA page
                             rt_clone.spawn(async move {
-                                if let Err(e) = handles_auth(&driver).await {
-                                    tx.send(format!("DUO auth failed: {}", e)).unwrap();
-                                    return;
-                                }
- 
-                                tokio::time::sleep(Duration::from_secs(5)).await;
-                                // Navigate to terms
-                                match navigate_to_terms(&driver).await {
-                                    Ok((terms, _curr_term)) => {
-                                        {
-                                            let mut lock = terms_hashmap_clone.lock().unwrap();
-                                            *lock = Some(terms.clone());
-                                        }
-                                        let terms_vec: Vec<SharedString> = terms.keys().map(|term| term.into()).collect();
+                                match duo::list_options(&driver).await {
+                                    Ok(options) if !options.is_empty() => {
+                                        let labels: Vec<SharedString> = options.iter().map(|option| option.label.as_str().into()).collect();
                                         slint::invoke_from_event_loop(move || {
                                             if let Some(ui) = login_ui_weak.upgrade() {
-                                                ui.set_current_page(Pages::Registration);
-                                                ui.set_available_terms(ModelRc::new(VecModel::from(terms_vec)));
+                                                ui.set_duo_options(ModelRc::new(VecModel::from(labels)));
+                                                ui.set_duo_status("Choose how to confirm your login".into());
                                             }
                                         }).unwrap();
-                                        
-
+                                    }
+                                    // Duo went straight to its default push
+                                    Ok(_) => {
+                                        set_duo_state(&login_ui_weak, duo::waiting_status(DuoMethod::Push).to_string(), true);
+                                        complete_duo(&driver, &session, &terms_hashmap_clone, login_ui_weak, &tx).await;
                                     }
                                     Err(e) => {
-                                        println!("{e}");
-                                        tx.send(format!("Error navigating to terms: {}", e)).unwrap();
+                                        let result = screenshot::on_error(&driver, "duo-options", Err(e)).await;
+                                        tx.send(result).unwrap();
                                     }
                                 }
                             });
                         }
-                        _ => {
-                            ui.set_message(result.into());
-                            ui.set_password("".into());
+                        Err(e) => {
+                            ui.set_current_page(Pages::Login);
+                            ui.set_message(login_error_message(&e).into());
+                            // only a rejected password needs to be typed again
+                            if let CusisError::BadCredentials = e {
+                                ui.set_password("".into());
+                            }
                         }
                     }
                 }
             }).unwrap();
         }
     });
+    // plans from the course catalog without signing in, the terms are the ones cached before
+    app.on_plan_offline({
+        let ui_weak = app.as_weak();
+        move || {
+            let Some(ui) = ui_weak.upgrade() else {
+                return;
+            };
+            let terms = match CourseCatalog::open_default().and_then(|catalog| catalog.terms()) {
+                Ok(terms) => terms,
+                Err(e) => {
+                    eprintln!("Course catalog unavailable: {}", e);
+                    vec![]
+                }
+            };
+            let cached = if terms.is_empty() {
+                "No course is cached yet, courses are cached once they are looked up on CUSIS".to_string()
+            } else {
+                "".to_string()
+            };
+            let terms: Vec<SharedString> = terms.iter().map(|term| term.into()).collect();
+            ui.set_signed_in(false);
+            if ui.get_course_source() == SourceChoice::Cusis.label() {
+                ui.set_course_source(SourceChoice::Catalog.label().into());
+            }
+            ui.set_cached_courses(cached.into());
+            ui.set_available_terms(ModelRc::new(VecModel::from(terms)));
+            ui.set_current_page(Pages::Registration);
+        }
+    });
+
     let rt_reg_clone = rt_reg_clone.clone();
     let (scheduler_tx, mut scheduler_rx) = channel::<Scheduler>(10);
-    let mut term_holder = Arc::new(Mutex::new(Option::None));
+    let term_holder = Arc::new(Mutex::new(Option::None));
     let term_holder_clone = term_holder.clone();
     let term_holder_clone_use = term_holder.clone();
     app.on_term_selected({ 
+        let term_ui_weak = app.as_weak();
         move |selected_value| {
-            let term_hashmap_use_clone: Arc<std::sync::Mutex<Option<HashMap<String, (WebElement, String)>>>> = term_hashmap_use_clone.clone();
+            let term_hashmap_use_clone: TermTable = term_hashmap_use_clone.clone();
             let selected_value = selected_value.to_string();
             let driver_clone = driver_clone.clone();
             
-            let mut term_holder_clone = term_holder_clone.clone();
+            let term_holder_clone = term_holder_clone.clone();
             // Clone the terms hashmap out of the mutex before entering async context
             let terms_opt = {
                 let lock = term_hashmap_use_clone.lock().unwrap();
                 lock.as_ref().cloned()
             };
+            if let Some(ui) = term_ui_weak.upgrade() {
+                ui.set_cached_courses(cached_courses_summary(&selected_value).into());
+            }
             rt_reg_clone.spawn(async move {
                 let mut term_holder_clone = term_holder_clone.lock().await;
                 *term_holder_clone = Some(selected_value.clone());              
+                // the terms are only read off CUSIS when signed in, offline the term only names the catalog entries
                 if let Some(terms) = terms_opt {
-                    select_school_terms(terms, selected_value, &driver_clone).await;
-                } else {
-                    panic!()
+                    let result = select_school_terms(terms, selected_value, &driver_clone).await;
+                    if let Err(e) = screenshot::on_error(&driver_clone, "select-term", result).await {
+                        eprintln!("Failed to select the term: {}", e);
+                    }
                 }
             });
         }
         
     });
     //let mut scheduler_holder: Arc<Mutex<Option<Scheduler>>> = Arc::new(Mutex::new(Option::None));
-    let scheduler_holder_clone: Option<Scheduler> = Option::None;
+    let campus = Arc::new(CampusMap::load());
     app.on_init_reg({
         // slint::invoke_from_event_loop(move || {
         // if let Some(ui) = reg_ui_weak_clone.upgrade() {
@@ -217,73 +401,160 @@ fn main() -> Result<(), PlatformError> {
         let rt_course_clone = rt_course_clone.clone();
         let driver_reg_clone = driver_reg.clone();
         let term_holder_clone = term_holder_clone_use.clone();
-        let scheduler_holder_clone = scheduler_holder_clone.clone();
         let scheduler_tx = scheduler_tx.clone();
         let reg_ui_weak = reg_ui_weak.clone();
-        move |course: SharedString, day_off: SharedString| {
+        let campus = campus.clone();
+        let session = session.clone();
+        move |course: SharedString, day_off: SharedString, earliest_start: SharedString, latest_end: SharedString| {
             let term_holder_clone = term_holder_clone.clone();
-            let scheduler_holder_clone = scheduler_holder_clone.clone();
             let driver_reg_clone: Arc<Mutex<WebDriver>> = driver_reg_clone.clone();
             let scheduler_tx = scheduler_tx.clone();
             let reg_ui_weak = reg_ui_weak.clone();
-            rt_course_clone.spawn(async move {
-                let courses_to_search: Arc<Vec<String>> = Arc::new(course.split_whitespace().map(String::from).collect());
-                let course_collection: Arc<DashMap<String, Vec<Course>>> = Arc::new(DashMap::new());
-                let course_collection_clone = course_collection.clone();
-                let (course_search_tx, course_search_rx) = channel::<CourseSearchTask>(10);
-                // Consumer thread: Process each search task one by one
-                let consumer_handle = tokio::spawn(
-                    process_search_tasks(
-                        course_search_rx,
-                        driver_reg_clone.clone(),
-                        course_collection_clone.clone(),
-                        false
-                    )
+            let session = session.clone();
+            let mut scheduler: Scheduler = Scheduler::new();
+            let mut freshness = FreshnessPolicy::from_env(false);
+            let mut keep_enrolled = false;
+            let mut signed_in = false;
+            let mut choice = SourceChoice::Cusis;
+            // "Any" (or anything that is not a HH:MM time) leaves the bound unconstrained
+            if let Ok(time) = NaiveTime::parse_from_str(&earliest_start, "%H:%M") {
+                scheduler.add_constraint(EarliestStartTime(time));
+            }
+            if let Ok(time) = NaiveTime::parse_from_str(&latest_end, "%H:%M") {
+                scheduler.add_constraint(LatestEndTime(time));
+            }
+            // the preference weights are read here since the UI handle cannot leave the event loop thread
+            if let Some(ui) = reg_ui_weak.upgrade() {
+                freshness.force_refresh = ui.get_refresh_catalog();
+                signed_in = ui.get_signed_in();
+                match SourceChoice::parse(&ui.get_course_source(), &ui.get_source_path()) {
+                    Some(source_choice) => choice = source_choice,
+                    None => eprintln!("Unknown course source {}, planning with CUSIS", ui.get_course_source())
+                }
+                // the enrolled classes can only be read off CUSIS
+                keep_enrolled = signed_in && ui.get_keep_enrolled();
+                if ui.get_strict_walking() {
+                    scheduler.add_constraint(WalkingTime(campus.clone()));
+                }
+                let blocked_days = parse_days(&ui.get_blocked_days());
+                if !blocked_days.is_empty() {
+                    scheduler.add_constraint(ForbiddenDays(blocked_days));
+                }
+                // the busy period is left out unless both ends are times and it does not end before it starts
+                if let (Ok(start), Ok(end)) = (NaiveTime::parse_from_str(&ui.get_busy_from(), "%H:%M"), NaiveTime::parse_from_str(&ui.get_busy_to(), "%H:%M")) {
+                    if start < end {
+                        scheduler.add_constraint(ForbiddenTimeWindow { days: parse_days(&ui.get_busy_days()), start, end });
+                    }
+                }
+                if ui.get_max_daily_hours() > 0 {
+                    scheduler.add_constraint(MaxDailyHours(ui.get_max_daily_hours() as f32));
+                }
+                let enrollment_mode = match ui.get_enrollment_mode().as_str() {
+                    "Allow waitlist" => EnrollmentMode::AllowWaitlist,
+                    "Include closed" => EnrollmentMode::IncludeClosed,
+                    _ => EnrollmentMode::OpenOnly
+                };
+                scheduler.add_constraint(SeatAvailability(enrollment_mode));
+                scheduler.add_objective(MinimizeGapTime, ui.get_gap_weight() as i64);
+                match Weekday::parse(&day_off) {
+                    Some(day) => scheduler.add_objective(PreferDayOff(day), ui.get_day_off_weight() as i64),
+                    None => scheduler.add_objective(MaximizeFreeDays, ui.get_day_off_weight() as i64)
+                }
+                scheduler.add_objective(AvoidEarlyMornings(NaiveTime::from_hms_opt(10, 30, 0).unwrap()), ui.get_early_morning_weight() as i64);
+                scheduler.add_objective(PreferCompactDays, ui.get_compact_days_weight() as i64);
+                scheduler.add_objective(MinimizeWalking(campus.clone()), ui.get_walking_weight() as i64);
+                scheduler.add_objective(
+                    ProtectLunchBreak {
+                        start: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+                        end: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                        minimum_minutes: 45
+                    },
+                    ui.get_lunch_break_weight() as i64
                 );
-                // Producer threads 
-                let ops: Arc<AtomicU64> = Arc::new(AtomicU64::new(0)); // Atomic Counter for fetching course 
-
-                let mut handles = vec![];
-                for _ in 0..courses_to_search.len() {
-                    let course_search_tx = course_search_tx.clone();
-                    let ops_clone: Arc<AtomicU64> = ops.clone();
-                    let course_clone: Arc<Vec<String>> = courses_to_search.clone();
-                    let code = Option::None;
-                    let term_holder_clone = term_holder_clone.clone();
-                    handles.push(tokio::spawn(async move {
-                        let term_holder_clone = term_holder_clone.lock().await;
-                        if let Some(term) = term_holder_clone.as_ref(){
-                            let index = ops_clone.fetch_add(1, Ordering::SeqCst);
-                            let course = course_clone[index as usize].clone(); 
-                            let term = term.to_string();
-
-                            if let Err(e) = course_search_tx.send(CourseSearchTask{course, term, code}).await{
-                                eprintln!("Failed to send Course Search Task {}", e);
+            }
+            rt_course_clone.spawn(async move {
+                let term = term_holder_clone.lock().await.clone().unwrap_or_default();
+                let mut course_names: Vec<String> = course.split_whitespace().map(String::from).collect();
+                let mut kept_courses: Vec<String> = vec![];
+                if keep_enrolled {
+                    let enrolled = {
+                        let driver = driver_reg_clone.lock().await;
+                        let result = class_schedule::read_enrolled_classes(&driver, &session, &term).await;
+                        screenshot::on_error(&driver, "class-schedule", result).await
+                    };
+                    match enrolled {
+                        Ok(courses) => {
+                            // a course already taken is not planned again, its classes only block their time
+                            course_names.retain(|name| !courses.iter().any(|enrolled| enrolled.course_name.eq_ignore_ascii_case(name)));
+                            for course in courses {
+                                kept_courses.push(course.course_name.clone());
+                                scheduler.add_commitment(course);
                             }
                         }
-                        
-                    }));
-                }
-                for handle in handles {
-                    let _ = handle.await;
+                        Err(e) => eprintln!("Failed to read the enrolled classes, planning without them: {}", e)
+                    }
                 }
-                drop(course_search_tx);
-                if let Err(e) = consumer_handle.await {
-                    eprintln!("Consumer task join failed: {:?}", e);
+                let cusis = signed_in.then(|| (driver_reg_clone.clone(), session.clone()));
+                let source = choice.build(freshness, cusis);
+                let mut status = format!("Loading courses from {}", source.name());
+                if !kept_courses.is_empty() {
+                    status.push_str(&format!(", planned around the enrolled {}", kept_courses.join(", ")));
                 }
-                slint::invoke_from_event_loop(move || {
+                slint::invoke_from_event_loop({
+                    let reg_ui_weak = reg_ui_weak.clone();
+                    move || {
+                        if let Some(ui) = reg_ui_weak.upgrade() {
+                            ui.set_reg_status(status.into());
+                        }
+                    }
+                }).unwrap();
+                let course_collection: Arc<DashMap<String, Vec<Course>>> = match source.fetch(&term, &course_names).await {
+                    Ok(courses) => Arc::new(courses),
+                    Err(e) => {
+                        eprintln!("Failed to load courses: {}", e);
+                        slint::invoke_from_event_loop(move || {
+                            if let Some(ui) = reg_ui_weak.upgrade() {
+                                ui.set_is_loading(false);
+                                // the session could not be signed back in, the login page starts a new one
+                                if let CusisError::SessionExpired | CusisError::BadCredentials | CusisError::DuoTimeout | CusisError::DuoDenied = e {
+                                    ui.set_current_page(Pages::Login);
+                                    ui.set_message(login_error_message(&e).into());
+                                }
+                                else{
+                                    ui.set_diagnosis(format!("Failed to load courses: {}", e).into());
+                                }
+                            }
+                        }).unwrap();
+                        return;
+                    }
+                };
+                slint::invoke_from_event_loop({
+                    let reg_ui_weak = reg_ui_weak.clone();
+                    move || {
                 if let Some(ui) = reg_ui_weak.upgrade() {
-                ui.set_is_loading(false);}}).unwrap();
-                let mut scheduler: Scheduler = Scheduler::new();
-                scheduler.generate_schedule(&(*course_collection.clone()).clone(), day_off.to_string());
-                scheduler_tx.send(scheduler).await;
+                ui.set_is_loading(false);}}}).unwrap();
+                scheduler.generate_schedule(&(*course_collection.clone()).clone());
+                if scheduler.get_candidate_count() == 0 {
+                    // stay on the registration page and explain what makes the courses unschedulable
+                    let diagnosis = match scheduler.diagnose_infeasibility(&course_collection) {
+                        Some(report) => report.summary(),
+                        None => "Sorry no possible schedule can be generated!".to_string()
+                    };
+                    slint::invoke_from_event_loop(move || {
+                        if let Some(ui) = reg_ui_weak.upgrade() {
+                            ui.set_diagnosis(diagnosis.into());
+                        }
+                    }).unwrap();
+                    return;
+                }
+                if let Err(e) = scheduler_tx.send(scheduler).await {
+                    eprintln!("Failed to hand the schedule over: {}", e);
+                }
               
             });
         }   
     }
 );
-    // if let Some(scheduler) = scheduler_holder_clone{
-    //     scheduler.get_schedule_with_best_fitness_score();
     //         slint::invoke_from_event_loop({
     //             let timetable_ui_weak = timetable_ui_weak.clone();
     //             move || {
@@ -293,41 +564,103 @@ fn main() -> Result<(), PlatformError> {
     //             }
     //         }).unwrap();
     //     }
+    let scheduler_holder: Arc<std::sync::Mutex<Option<Scheduler>>> = Arc::new(std::sync::Mutex::new(None));
     let timetable_ui_weak = app.as_weak();
-    rt_clone.spawn(async move {
-        while let Some(mut scheduler) = scheduler_rx.recv().await{
-            let timetable_ui_weak = timetable_ui_weak.clone();
-            
-            slint::invoke_from_event_loop({
+    rt_clone.spawn({
+        let scheduler_holder = scheduler_holder.clone();
+        async move {
+            while let Some(scheduler) = scheduler_rx.recv().await{
                 let timetable_ui_weak = timetable_ui_weak.clone();
-                move || {
+                let scheduler_holder = scheduler_holder.clone();
+                slint::invoke_from_event_loop(move || {
                     if let Some(ui) = timetable_ui_weak.upgrade() {
-                       ui.set_current_page(Pages::TimeTable);
-                        if let Some(best_schedule) = scheduler.get_next_schedule(1){
-                            println!("{:?}", best_schedule.0);
-                            let best_schedule_vec: Vec<SharedString> = best_schedule.0.iter().map(|data| data.into()).collect();
-                            let timetable_model = Rc::new(VecModel::from(best_schedule_vec));
-                            ui.set_current_timetable(timetable_model.into());
-                       }
+                        ui.set_current_page(Pages::TimeTable);
+                        let mut lock = scheduler_holder.lock().unwrap();
+                        *lock = Some(scheduler);
+                        if let Some(scheduler) = lock.as_mut() {
+                            show_schedule(&ui, scheduler, 1);
+                        }
                     }
+                }).unwrap();
+            }
+        }
+    });
+
+    app.on_get_next_schedule({
+        let scheduler_holder = scheduler_holder.clone();
+        let timetable_ui_weak = app.as_weak();
+        move || {
+            if let Some(ui) = timetable_ui_weak.upgrade() {
+                if let Some(scheduler) = scheduler_holder.lock().unwrap().as_mut() {
+                    show_schedule(&ui, scheduler, 1);
                 }
-            }).unwrap();
-            
-    }});
-   
-//    app.on_get_next_schedule({
-//     if let Some(best_schedule) = scheduler.get_next_schedule(1){
-//                             println!("{:?}", best_schedule.0);
-//                             let best_schedule_vec: Vec<SharedString> = best_schedule.0.iter().map(|data| data.into()).collect();
-//                             let timetable_model = Rc::new(VecModel::from(best_schedule_vec));
-//                             ui.set_current_timetable(timetable_model.into());
-//                        }
-//    });
-
-//    app.on_get_prev_schedule({
-    
-//    });
-    
+            }
+        }
+    });
+
+    app.on_get_prev_schedule({
+        let scheduler_holder = scheduler_holder.clone();
+        let timetable_ui_weak = app.as_weak();
+        move || {
+            if let Some(ui) = timetable_ui_weak.upgrade() {
+                if let Some(scheduler) = scheduler_holder.lock().unwrap().as_mut() {
+                    show_schedule(&ui, scheduler, 0);
+                }
+            }
+        }
+    });
+
+    // Compares the CUSIS shopping cart with the schedule on display and adds, removes or swaps classes to match it.
+    // The cart only changes once a dry run of the same action on the same schedule has been confirmed
+    app.on_cart_action({
+        let scheduler_holder = scheduler_holder.clone();
+        let term_holder = term_holder.clone();
+        let driver = driver_reg.clone();
+        let session = session.clone();
+        let rt = rt_clone.clone();
+        let cart_ui_weak = app.as_weak();
+        move |action| {
+            let action_name = action.to_string();
+            let Some(action) = cart::CartAction::parse(&action) else {
+                eprintln!("Unknown cart action {}", action);
+                return;
+            };
+            let Some(schedule) = scheduler_holder.lock().unwrap().as_ref().and_then(|scheduler| scheduler.get_current_schedule()) else {
+                return;
+            };
+            let Some(ui) = cart_ui_weak.upgrade() else {
+                return;
+            };
+            let confirmed = ui.get_cart_pending() == action_name.as_str();
+            let rank = ui.get_current_schedule_rank();
+            ui.set_cart_busy(true);
+            ui.set_cart_pending("".into());
+            ui.set_cart_report("Reading the shopping cart...".into());
+            let term_holder = term_holder.clone();
+            let driver = driver.clone();
+            let session = session.clone();
+            let cart_ui_weak = cart_ui_weak.clone();
+            rt.spawn(async move {
+                let term = term_holder.lock().await.clone().unwrap_or_default();
+                let (summary, needs_confirmation) = match cart::apply(driver, session, &term, schedule, action, confirmed).await {
+                    Ok(outcome) => (outcome.summary, outcome.needs_confirmation),
+                    Err(e) => (format!("The shopping cart could not be updated: {}", e), false)
+                };
+                println!("{}", summary);
+                slint::invoke_from_event_loop(move || {
+                    if let Some(ui) = cart_ui_weak.upgrade() {
+                        ui.set_cart_busy(false);
+                        ui.set_cart_report(summary.into());
+                        // the confirmation only stands for the schedule that was reviewed
+                        if needs_confirmation && ui.get_current_schedule_rank() == rank {
+                            ui.set_cart_pending(action_name.into());
+                        }
+                    }
+                }).unwrap();
+            });
+        }
+    });
+
     // Run the UI
     app.run()?;
 
@@ -339,4 +672,121 @@ fn main() -> Result<(), PlatformError> {
     })?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// reads the terms off the class search page and moves the app on to registration
+async fn open_registration(
+    driver: &WebDriver,
+    terms_hashmap: &TermTable,
+    ui_weak: Weak<App>
+) -> CusisResult<()> {
+    let (terms, _curr_term) = screenshot::on_error(driver, "navigate-to-terms", navigate_to_terms(driver).await).await?;
+    *terms_hashmap.lock().unwrap() = Some(terms.clone());
+    let terms_vec: Vec<SharedString> = terms.keys().map(|term| term.into()).collect();
+    slint::invoke_from_event_loop(move || {
+        if let Some(ui) = ui_weak.upgrade() {
+            ui.set_signed_in(true);
+            ui.set_current_page(Pages::Registration);
+            ui.set_available_terms(ModelRc::new(VecModel::from(terms_vec)));
+        }
+    }).unwrap();
+    Ok(())
+}
+
+// Waits for the Duo request that was just sent and moves on to registration once it is approved. A denied or
+// expired request keeps the 2FA page open to choose again, anything else goes back to the login page through `tx`
+async fn complete_duo(
+    driver: &WebDriver,
+    session: &Session,
+    terms_hashmap: &TermTable,
+    ui_weak: Weak<App>,
+    tx: &UnboundedSender<CusisResult<()>>
+) {
+    let on_status = {
+        let ui_weak = ui_weak.clone();
+        move |status: String| set_duo_state(&ui_weak, status, true)
+    };
+    match screenshot::on_error(driver, "duo", handles_auth(driver, on_status).await).await {
+        Ok(()) => {}
+        Err(CusisError::DuoDenied) => {
+            set_duo_state(&ui_weak, "The Duo request was denied, choose how to confirm again".to_string(), false);
+            return;
+        }
+        Err(CusisError::DuoTimeout) => {
+            set_duo_state(&ui_weak, "The Duo request timed out, choose how to confirm again".to_string(), false);
+            return;
+        }
+        Err(e) => {
+            tx.send(Err(e)).unwrap();
+            return;
+        }
+    }
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    if let Err(e) = session.save_cookies(driver).await {
+        eprintln!("Failed to save the session cookies: {}", e);
+    }
+    // Navigate to terms
+    if let Err(e) = open_registration(driver, terms_hashmap, ui_weak).await {
+        println!("{e}");
+        tx.send(Err(e)).unwrap();
+    }
+}
+
+// "Wed Sat" or "Wednesday, Saturday", words that are not days are skipped
+fn parse_days(text: &str) -> Vec<Weekday> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(Weekday::parse)
+        .collect()
+}
+
+// the courses the catalog already holds for `term`, which are all an offline plan can use
+fn cached_courses_summary(term: &str) -> String {
+    match CourseCatalog::open_default().and_then(|catalog| catalog.courses_in_term(term)) {
+        Ok(courses) if courses.is_empty() => format!("No course of {} is cached", term),
+        Ok(courses) => format!("Cached for {}: {}", term, courses.join(" ")),
+        Err(e) => format!("Course catalog unavailable: {}", e)
+    }
+}
+
+// shows `status` on the 2FA page, `waiting` while Duo has a request out
+fn set_duo_state(ui_weak: &Weak<App>, status: String, waiting: bool) {
+    let ui_weak = ui_weak.clone();
+    let _ = slint::invoke_from_event_loop(move || {
+        if let Some(ui) = ui_weak.upgrade() {
+            ui.set_duo_status(status.into());
+            ui.set_duo_waiting(waiting);
+        }
+    });
+}
+
+// what the login page says when signing in, Duo or loading the terms fails
+fn login_error_message(error: &CusisError) -> String {
+    match error {
+        CusisError::BadCredentials => "Incorrect student ID or password".to_string(),
+        CusisError::DuoTimeout => "Duo was not approved in time, please log in again".to_string(),
+        CusisError::DuoDenied => "The Duo request was denied, please log in again".to_string(),
+        CusisError::SessionExpired => "Your CUSIS session expired, please log in again".to_string(),
+        CusisError::PageLayoutChanged(_) => format!("{}. CUSIS may have changed, please report this with the saved screenshot", error),
+        _ => format!("Login failed: {}", error)
+    }
+}
+
+// moves the scheduler one candidate in `direction` and renders it with its score report on the timetable page
+fn show_schedule(ui: &App, scheduler: &mut Scheduler, direction: i8) {
+    if let Some((timetable, _, report)) = scheduler.get_next_schedule(direction) {
+        let rank = scheduler.get_current_rank();
+        let timetable_vec: Vec<SharedString> = timetable.iter().map(|data| data.into()).collect();
+        ui.set_current_timetable(Rc::new(VecModel::from(timetable_vec)).into());
+        ui.set_current_schedule_rank(rank as i32);
+        ui.set_cart_pending("".into());
+        ui.set_schedule_count(scheduler.get_candidate_count() as i32);
+
+        let mut report_text = report.summary();
+        if let Some(explanation) = scheduler.explain_rank(rank as usize) {
+            report_text.push_str("\n\n");
+            report_text.push_str(&explanation);
+        }
+        ui.set_score_report(report_text.into());
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::NaiveTime;
+use crate::course::course_manager::{CourseOffering, SeatStatus, Seats, Section};
+use crate::course::meeting::{Meeting, Weekday};
+
+fn is_instructor_line(line: &str) -> bool {
+    line.contains("Mr.") || line.contains("Ms.") || line.contains("Dr.") || line.contains("Prof.") || line.contains("Professor")
+}
+
+// CUSIS prints the venue right under the time of a meeting, anything that looks like another field is not a venue
+fn parse_venue(line: Option<&&str>) -> String {
+    match line {
+        Some(line) if !line.is_empty()
+            && !line.contains(" to ")
+            && Weekday::find_all_in(line).is_empty()
+            && Section::parse_header(line).is_none()
+            && !is_instructor_line(line) => line.to_string(),
+        _ => String::new()
+    }
+}
+
+// reads counts such as "Class Capacity 60", the number may also sit on the line below the label
+fn parse_count(lines: &[&str], label: &str) -> Option<u32> {
+    let i = lines.iter().position(|line| line.starts_with(label))?;
+    let rest = lines[i][label.len()..].trim().trim_start_matches(':').trim();
+    let number = if rest.is_empty() { lines.get(i + 1)?.trim() } else { rest };
+    number.split_whitespace().next()?.parse::<u32>().ok()
+}
+
+// a function to reformate one scraped class option and add its sections to the course offering
+pub fn data_formating(data: &str, offering: &mut CourseOffering){
+    let lines: Vec<&str> = data.lines().map(|l| l.trim()).collect(); // Collect lines into a Vec
+    let mut sections: Vec<Section> = Vec::new();
+    let mut meetings: Vec<Meeting> = Vec::new();
+    let mut instructor = String::new();
+    let mut seats = Seats {
+        capacity: parse_count(&lines, "Class Capacity"),
+        enrolled: parse_count(&lines, "Enrollment Total"),
+        waitlist: parse_count(&lines, "Wait List Total"),
+        ..Seats::default()
+    };
+    
+    for (i, line) in lines.iter().enumerate() {
+        if i == 1 {
+            match SeatStatus::parse(line) {
+                Some(status) => seats.status = status,
+                None => return // Return if the row has no seat status
+            }
+        }
+        if let Some(section) = Section::parse_header(line) {
+            if !sections.iter().any(|existing: &Section| existing.class_number == section.class_number) {
+                sections.push(section);
+            }
+        }
+        
+        let weekdays = Weekday::find_all_in(line);
+        if !weekdays.is_empty() {
+            // Check if there's a next line
+            if i + 1 < lines.len() {
+                let time_slot = lines[i + 1]; // Access the next line to get the time
+                let parts: Vec<&str> = time_slot.split(" to ").collect(); // Split the time slot into start and end time
+                if parts.len() != 2 {
+                    eprintln!("Failed to parse time slot: {}", time_slot);
+                    continue;
+                }
+                let (start_time, end_time) = match (
+                NaiveTime::parse_from_str(parts[0], "%I:%M%P"),
+                NaiveTime::parse_from_str(parts[1], "%I:%M%P"),
+                ) {
+                    (Ok(start_time), Ok(end_time)) => (start_time, end_time),
+                    _ => {
+                        eprintln!("Failed to parse times: {} - {}", parts[0], parts[1]);
+                        continue;
+                    }
+                };
+                let room = parse_venue(lines.get(i + 2));
+                for weekday in weekdays{
+                    match Meeting::new(weekday, start_time, end_time) {
+                        Some(mut meeting) => {
+                            meeting.room = room.clone();
+                            if !meetings.contains(&meeting){
+                                meetings.push(meeting);
+                            }
+                        }
+                        None => eprintln!("Skipping empty time slot: {}", time_slot)
+                    }
+                }
+            }
+        }
+        else if is_instructor_line(line){
+            instructor = line.to_string();
+        }
+        
+        if !instructor.is_empty() && !meetings.is_empty() && !sections.is_empty() {
+            break;
+        }
+    }
+    if !meetings.is_empty() {
+        for section in sections.iter_mut() {
+            section.seats = seats;
+        }
+        offering.add_option(instructor, sections, meetings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_formating_keeps_venue() {
+        let data = "( 8232 ) - - LEC\nOpen\nMonday Wednesday\n2:30pm to 4:15pm\nLady Shaw Bldg LT1\nProfessor Lauren Marcelyn PICK\n( 8810 ) -T01 - TUT";
+        let mut offering = CourseOffering::new("CSCI3180".to_string());
+        data_formating(data, &mut offering);
+        let (instructor, sections, meetings) = &offering.options[0];
+        assert_eq!(instructor, "Professor Lauren Marcelyn PICK");
+        assert_eq!(sections.len(), 1);
+        assert_eq!(meetings.len(), 2);
+        assert!(meetings.iter().all(|meeting| meeting.room == "Lady Shaw Bldg LT1"));
+        assert_eq!(meetings[1].to_string(), "Wednesday 14:30 - 16:15 @ Lady Shaw Bldg LT1");
+    }
+
+    #[test]
+    fn test_data_formating_keeps_seat_counts() {
+        let data = "( 8810 ) -T01 - TUT\nWait List\nTuesday\n3:30pm to 4:15pm\nProfessor Lauren Marcelyn PICK\nClass Capacity 30\nEnrollment Total\n30\nWait List Total 4";
+        let mut offering = CourseOffering::new("CSCI3180".to_string());
+        data_formating(data, &mut offering);
+        let seats = offering.options[0].1[0].seats;
+        assert_eq!(seats, Seats { status: SeatStatus::Waitlist, capacity: Some(30), enrolled: Some(30), waitlist: Some(4) });
+        assert_eq!(seats.badge(), "Waitlist 4");
+
+        let mut offering = CourseOffering::new("CSCI3180".to_string());
+        data_formating("( 8810 ) -T01 - TUT\nCancelled\nTuesday\n3:30pm to 4:15pm", &mut offering);
+        assert!(offering.options.is_empty());
+    }
+}
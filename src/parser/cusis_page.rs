@@ -0,0 +1,364 @@
+use scraper::{ElementRef, Html, Selector};
+use chrono::NaiveTime;
+use crate::course::course_manager::{Component, Course, CourseOffering, Section};
+use crate::course::meeting::{Meeting, Weekday};
+use super::class_text::data_formating;
+
+// One course listed on the "Class Search Results" page
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub title: String,
+    pub link: Option<String>
+}
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("invalid built-in selector")
+}
+
+// the visible text of an element, one trimmed line per text node
+fn element_lines(element: ElementRef) -> Vec<String> {
+    element.text()
+        .map(|text| text.trim())
+        .filter(|text| !text.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// Parses the class grid of a saved or live "Course Information" page into the options of `course_name`
+pub fn parse_course_information(html: &str, course_name: &str) -> CourseOffering {
+    let document = Html::parse_document(html);
+    let mut offering = CourseOffering::new(course_name.to_string());
+    for grid in document.select(&selector(".ps_grid-body")) {
+        for row in grid.select(&selector("tr")) {
+            let mut lines = element_lines(row);
+            // the grid numbers its rows, the class text starts after that number
+            while lines.first().is_some_and(|line| line.chars().all(|c| c.is_ascii_digit())) {
+                lines.remove(0);
+            }
+            if !lines.is_empty() {
+                data_formating(&lines.join("\n"), &mut offering);
+            }
+        }
+    }
+    offering
+}
+
+// the class rows of a "Course Information" page, in the order WebDriver finds them with the same selector
+pub const CLASS_ROW_SELECTOR: &str = ".ps_grid-body tr";
+
+// class numbers of every section header ("( 8232 ) - - LEC") among `lines`
+fn class_numbers(lines: &[String]) -> Vec<u32> {
+    lines.iter()
+        .filter_map(|line| Section::parse_header(line))
+        .map(|section| section.class_number)
+        .collect()
+}
+
+// Indices (in CLASS_ROW_SELECTOR order) of the rows offering exactly the sections `wanted`. A lecture is repeated on
+// the row of each of its tutorials, so a row only matches when it lists no other section either.
+pub fn find_class_rows(html: &str, wanted: &[u32]) -> Vec<usize> {
+    let mut wanted = wanted.to_vec();
+    wanted.sort();
+    let document = Html::parse_document(html);
+    document.select(&selector(CLASS_ROW_SELECTOR))
+        .enumerate()
+        .filter(|(_, row)| {
+            let mut offered = class_numbers(&element_lines(*row));
+            offered.sort();
+            offered == wanted
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+// What CUSIS answered after Submit: whether it took the classes, its message and the class numbers it mentions
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubmitResult {
+    pub succeeded: bool,
+    pub message: String,
+    pub class_numbers: Vec<u32>
+}
+
+fn is_result_message(line: &str) -> bool {
+    let line = line.to_lowercase();
+    ["success", "error", "unable", "has been added", "not added"].iter().any(|word| line.contains(word))
+}
+
+// class numbers a result message names outright, "Class 8232" or "Class Nbr 8232"; a year or a count is not one
+fn named_class_numbers(message: &str) -> Vec<u32> {
+    let words: Vec<String> = message.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_ascii_alphanumeric()).to_lowercase())
+        .collect();
+    words.windows(2)
+        .filter(|pair| matches!(pair[0].as_str(), "class" | "nbr"))
+        .filter_map(|pair| pair[1].parse::<u32>().ok())
+        .collect()
+}
+
+// Reads the result of a submission, e.g. "Success: This class has been added to your Shopping Cart" together with the
+// class rows it lists. Class numbers only come from the rows CUSIS wrote a result into and from classes the message
+// names, the page may list other classes or the term's year elsewhere.
+pub fn parse_submit_result(html: &str) -> SubmitResult {
+    let document = Html::parse_document(html);
+    let lines = element_lines(document.root_element());
+    let messages: Vec<&String> = lines.iter().filter(|line| is_result_message(line)).collect();
+    let message = messages.iter().map(|line| line.as_str()).collect::<Vec<&str>>().join(" ");
+    let lowered = message.to_lowercase();
+    let succeeded = (lowered.contains("success") || lowered.contains("has been added"))
+        && !lowered.contains("error") && !lowered.contains("unable") && !lowered.contains("not added");
+    let mut numbers: Vec<u32> = document.select(&selector(CLASS_ROW_SELECTOR))
+        .map(element_lines)
+        .filter(|row| row.iter().any(|line| is_result_message(line)))
+        .flat_map(|row| class_numbers(&row))
+        .collect();
+    for line in messages {
+        numbers.extend(named_class_numbers(line));
+    }
+    numbers.sort();
+    numbers.dedup();
+    SubmitResult { succeeded, message, class_numbers: numbers }
+}
+
+// the text of the first element under `parent` whose id starts with `id_prefix`, PeopleSoft suffixes ids with the row
+fn text_by_id_prefix(parent: ElementRef, id_prefix: &str) -> String {
+    parent.select(&selector(&format!("[id^='{}']", id_prefix)))
+        .next()
+        .map(|element| element_lines(element).join(" "))
+        .unwrap_or_default()
+}
+
+// "Tutorial" and the like as the class schedule spells them out
+fn component_from_name(name: &str) -> Component {
+    match name.trim().to_lowercase().as_str() {
+        "lecture" => Component::Lecture,
+        "tutorial" => Component::Tutorial,
+        "laboratory" | "lab" => Component::Lab,
+        "classwork" => Component::Classwork,
+        "project" => Component::Project,
+        "seminar" => Component::Seminar,
+        _ => Component::parse(name)
+    }
+}
+
+// the meetings of "MoWe 2:30PM - 4:15PM", nothing for "TBA"
+fn parse_class_schedule_times(text: &str) -> Vec<Meeting> {
+    let Some((days, times)) = text.trim().split_once(' ') else {
+        return vec![];
+    };
+    let Some((start, end)) = times.split_once(" - ") else {
+        return vec![];
+    };
+    let (Ok(start), Ok(end)) = (NaiveTime::parse_from_str(start.trim(), "%I:%M%p"), NaiveTime::parse_from_str(end.trim(), "%I:%M%p")) else {
+        return vec![];
+    };
+    days.as_bytes()
+        .chunks(2)
+        .filter_map(|abbreviation| std::str::from_utf8(abbreviation).ok())
+        .filter_map(|abbreviation| Weekday::ALL.into_iter().find(|day| day.name()[..2].eq_ignore_ascii_case(abbreviation)))
+        .filter_map(|day| Meeting::new(day, start, end))
+        .collect()
+}
+
+// Reads the enrolled courses off "My Class Schedule", one Course per course with every section the student sits in.
+// Dropped and wait listed courses are left out since they do not take up any time.
+pub fn parse_enrolled_classes(html: &str) -> Vec<Course> {
+    let document = Html::parse_document(html);
+    let mut courses = vec![];
+    for group in document.select(&selector(".PSGROUPBOXWBO")) {
+        let Some(course_name) = group.select(&selector(".PAGROUPDIVIDER")).next()
+            .and_then(|title| course_code(&element_lines(title).join(" "))) else {
+            continue;
+        };
+        let status = text_by_id_prefix(group, "STATUS");
+        if !status.is_empty() && !status.eq_ignore_ascii_case("Enrolled") {
+            continue;
+        }
+        let mut sections: Vec<Section> = vec![];
+        let mut instructor = String::new();
+        for row in group.select(&selector("tr[id^='trCLASS_MTG_VW']")) {
+            if let Ok(class_number) = text_by_id_prefix(row, "DERIVED_CLS_DTL_CLASS_NBR").parse::<u32>() {
+                let label = text_by_id_prefix(row, "MTG_SECTION").trim_start_matches('-').trim().to_string();
+                sections.push(Section::new(component_from_name(&text_by_id_prefix(row, "MTG_COMP")), class_number, label));
+            }
+            // a row without a class number is one more meeting of the section above it
+            let Some(section) = sections.last_mut() else {
+                continue;
+            };
+            let room = text_by_id_prefix(row, "MTG_LOC");
+            for mut meeting in parse_class_schedule_times(&text_by_id_prefix(row, "MTG_SCHED")) {
+                meeting.room = room.clone();
+                section.meetings.push(meeting);
+            }
+            if instructor.is_empty() {
+                instructor = text_by_id_prefix(row, "DERIVED_CLS_DTL_SSR_INSTR_LONG");
+            }
+        }
+        if !sections.is_empty() {
+            courses.push(Course::create_course_time(course_name, instructor, sections));
+        }
+    }
+    courses
+}
+
+// Lists the course titles (PTS_LIST_TITLE$n) of a "Class Search Results" page in the order CUSIS shows them
+pub fn parse_search_results(html: &str) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    document.select(&selector("[id^='PTS_LIST_TITLE']"))
+        .map(|title| SearchResult {
+            title: element_lines(title).join(" "),
+            link: title.value().attr("href").map(String::from)
+        })
+        .collect()
+}
+
+// "CSCI3180" of a "CSCI 3180 - Principles of Programming Languages" title
+fn course_code(line: &str) -> Option<String> {
+    let code = line.split_once(" - ")?.0.replace(' ', "");
+    // class lines such as "( 8232 ) - - LEC" split the same way
+    (code.starts_with(|c: char| c.is_ascii_alphabetic()) && code.chars().any(|c| c.is_ascii_digit())).then_some(code)
+}
+
+// One class already in the shopping cart: the course and the class numbers of its primary and related sections
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartEntry {
+    pub course: String,
+    pub class_numbers: Vec<u32>
+}
+
+// Lists the rows of the "Shopping Cart" page, a course is read off the "CSCI 3180 - ..." line of its row
+pub fn parse_shopping_cart(html: &str) -> Vec<CartEntry> {
+    let document = Html::parse_document(html);
+    document.select(&selector(".ps_grid-row"))
+        .filter_map(|row| {
+            let lines = element_lines(row);
+            let course = lines.iter().find_map(|line| course_code(line))?;
+            let class_numbers = class_numbers(&lines);
+            if class_numbers.is_empty() {
+                return None;
+            }
+            Some(CartEntry { course, class_numbers })
+        })
+        .collect()
+}
+
+// Blanks out what identifies the student in a saved page: the student ID, the CUHK mail address, the name in the
+// portal header and the session ids PeopleSoft keeps in hidden inputs. What is left can be kept as a test fixture.
+pub fn anonymize_page(html: &str) -> String {
+    let replacements = [
+        (r"\b11\d{8}\b", "1155000000"),
+        (r"[A-Za-z0-9._%+-]+@link\.cuhk\.edu\.hk", "student@link.cuhk.edu.hk"),
+        (r#"(id=['"]PT_HEADERNAME[^>]*>)[^<]*"#, "${1}Student Name"),
+        (r#"(<input[^>]*name=['"](?:ICSID|ICStateNum|ICElementNum)['"][^>]*value=['"])[^'"]*"#, "${1}0")
+    ];
+    let mut page = html.to_string();
+    for (pattern, replacement) in replacements {
+        let pattern = fancy_regex::Regex::new(pattern).expect("invalid built-in pattern");
+        page = pattern.replace_all(&page, replacement).into_owned();
+    }
+    page
+}
+
+// The classes listed on a "Review Class Selection" page, one line per class row, which is what Submit would send
+pub fn parse_class_review(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    document.select(&selector(".ps_grid-row"))
+        .map(|row| element_lines(row).join(" "))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::course::course_manager::SeatStatus;
+
+    #[test]
+    fn test_parse_course_information_fixture() {
+        let offering = parse_course_information(include_str!("fixtures/course_information.html"), "CSCI3180");
+        assert_eq!(offering.options.len(), 3);
+        let combinations = offering.combinations();
+        assert_eq!(combinations.iter().map(|course| course.class_numbers()).collect::<Vec<_>>(), vec![vec![8232, 8810], vec![8232, 8885], vec![8232, 8188]]);
+        let lecture = combinations[0].section(&Component::Lecture).unwrap();
+        assert_eq!(lecture.meetings.len(), 1);
+        assert_eq!(lecture.meetings[0].weekday, Weekday::Monday);
+        assert_eq!(lecture.meetings[0].room, "Lady Shaw Bldg LT1");
+        let tutorial = combinations[1].section(&Component::Tutorial).unwrap();
+        assert_eq!(tutorial.seats.status, SeatStatus::Waitlist);
+        assert_eq!(tutorial.meetings[0].to_string(), "Wednesday 16:30 - 17:15 @ Mong Man Wai Bldg 710");
+        // the closed row is kept, the scheduler decides whether closed sections may be used
+        assert_eq!(offering.options[2].1[1].seats.status, SeatStatus::Closed);
+    }
+
+    #[test]
+    fn test_parse_search_results_fixture() {
+        let results = parse_search_results(include_str!("fixtures/class_search_results.html"));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "CSCI 3180 - Principles of Programming Languages");
+        assert_eq!(results[0].link.as_deref(), Some("https://cusis.cuhk.edu.hk/psc/CSPRD_4/EMPLOYEE/SA/c/SSR_STUDENT_FL.SSR_CRSE_INFO_FL.GBL?CRSE_ID=001234"));
+        assert!(parse_search_results("<html><body>No classes found</body></html>").is_empty());
+    }
+
+    #[test]
+    fn test_parse_shopping_cart_fixture() {
+        let cart = parse_shopping_cart(include_str!("fixtures/shopping_cart.html"));
+        assert_eq!(cart, vec![
+            CartEntry { course: "CSCI3180".to_string(), class_numbers: vec![8232, 8885] },
+            CartEntry { course: "ENGG1110".to_string(), class_numbers: vec![5521] }
+        ]);
+        assert!(parse_shopping_cart("<html><body>Your shopping cart is empty</body></html>").is_empty());
+    }
+
+    #[test]
+    fn test_find_class_rows_matches_the_whole_combination() {
+        let html = include_str!("fixtures/course_information.html");
+        assert_eq!(find_class_rows(html, &[8885, 8232]), vec![1]);
+        // the lecture alone is on every row but is not a combination of its own
+        assert!(find_class_rows(html, &[8232]).is_empty());
+        assert!(find_class_rows(html, &[8232, 8853]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_submit_result_fixture() {
+        let result = parse_submit_result(include_str!("fixtures/submit_result.html"));
+        assert!(result.succeeded);
+        assert_eq!(result.class_numbers, vec![8232, 8885]);
+        let rejected = parse_submit_result("<html><body><p>Error: Unable to add class 8232, it is full.</p></body></html>");
+        assert!(!rejected.succeeded);
+        assert_eq!(rejected.class_numbers, vec![8232]);
+    }
+
+    #[test]
+    fn test_submit_result_ignores_other_classes_and_years() {
+        let result = parse_submit_result(include_str!("fixtures/submit_result_with_cart.html"));
+        assert!(result.succeeded);
+        // 8249 is a cart row without a result and 2025 is the term
+        assert_eq!(result.class_numbers, vec![8232, 8885]);
+    }
+
+    #[test]
+    fn test_parse_enrolled_classes_fixture() {
+        let courses = parse_enrolled_classes(include_str!("fixtures/class_schedule.html"));
+        // the dropped course is not a commitment
+        assert_eq!(courses.len(), 1);
+        assert_eq!(courses[0].course_name, "CSCI3100");
+        assert_eq!(courses[0].class_numbers(), vec![8249, 8853]);
+        let lecture = courses[0].section(&Component::Lecture).unwrap();
+        assert_eq!(lecture.meetings.iter().map(|meeting| meeting.to_string()).collect::<Vec<_>>(), vec![
+            "Tuesday 12:30 - 14:15 @ Lady Shaw Bldg LT2",
+            "Monday 11:30 - 12:15 @ Lady Shaw Bldg LT2"
+        ]);
+        assert_eq!(courses[0].section(&Component::Tutorial).unwrap().label, "T01");
+    }
+
+    #[test]
+    fn test_anonymize_page_keeps_the_classes() {
+        let page = r#"<span id="PT_HEADERNAME">CHAN Tai Man</span><input type="hidden" name="ICSID" id="ICSID" value="aB3dE9fGh">
+<p>1155123456@link.cuhk.edu.hk (1155123456)</p><span>( 8232 ) - - LEC</span>"#;
+        let anonymized = anonymize_page(page);
+        for personal in ["CHAN Tai Man", "aB3dE9fGh", "1155123456"] {
+            assert!(!anonymized.contains(personal), "{} is left in {}", personal, anonymized);
+        }
+        assert!(anonymized.contains("student@link.cuhk.edu.hk (1155000000)"));
+        assert!(anonymized.contains("( 8232 ) - - LEC"));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod class_text;
+pub mod cusis_page;
\ No newline at end of file
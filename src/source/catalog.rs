@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::Utc;
+use dashmap::DashMap;
+use crate::course::catalog::{CourseCatalog, FreshnessPolicy};
+use crate::course::course_manager::Course;
+use crate::error::CusisResult;
+use super::CourseSource;
+
+// Serves courses from the local catalog. Courses that are missing or stale are fetched from
+// `fallback` and written back, without a fallback every cached course is used however old it is.
+pub struct CatalogSource {
+    fallback: Option<Arc<dyn CourseSource>>,
+    freshness: FreshnessPolicy
+}
+
+impl CatalogSource {
+    pub fn new(fallback: Option<Arc<dyn CourseSource>>, freshness: FreshnessPolicy) -> CatalogSource {
+        CatalogSource { fallback, freshness }
+    }
+}
+
+#[async_trait]
+impl CourseSource for CatalogSource {
+    fn name(&self) -> String {
+        match &self.fallback {
+            Some(fallback) => format!("course catalog, then {}", fallback.name()),
+            None => "course catalog".to_string()
+        }
+    }
+
+    async fn fetch(&self, term: &str, course_names: &[String]) -> CusisResult<DashMap<String, Vec<Course>>> {
+        let course_collection = DashMap::new();
+        let mut to_fetch = vec![];
+        {
+            let catalog = match CourseCatalog::open_default() {
+                Ok(catalog) => Some(catalog),
+                Err(e) => {
+                    eprintln!("Course catalog unavailable: {}", e);
+                    None
+                }
+            };
+            for course_name in course_names {
+                let cached = catalog.as_ref().and_then(|catalog| catalog.load(term, course_name).ok().flatten());
+                match cached {
+                    Some(cached) if self.fallback.is_none() || self.freshness.is_fresh(&cached, Utc::now()) => {
+                        println!("{} loaded from the course catalog", course_name);
+                        course_collection.insert(course_name.clone(), cached.options);
+                    }
+                    _ => to_fetch.push(course_name.clone())
+                }
+            }
+        }
+
+        if let (Some(fallback), false) = (&self.fallback, to_fetch.is_empty()) {
+            let fetched = fallback.fetch(term, &to_fetch).await?;
+            let fetched_at = Utc::now();
+            // the catalog is opened again since a connection cannot be held across the fetch
+            if let Ok(catalog) = CourseCatalog::open_default() {
+                for entry in fetched.iter() {
+                    if let Err(e) = catalog.save(term, entry.key(), entry.value(), fetched_at) {
+                        eprintln!("Failed to cache {}: {}", entry.key(), e);
+                    }
+                }
+            }
+            for (course_name, options) in fetched {
+                course_collection.insert(course_name, options);
+            }
+        }
+        Ok(course_collection)
+    }
+}
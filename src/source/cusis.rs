@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use thirtyfour::WebDriver;
+use tokio::sync::Mutex;
+use tokio::sync::mpsc::channel;
+use crate::course::course_manager::Course;
+use crate::error::{CusisError, CusisResult};
+use crate::webdriver::scrape::{process_search_tasks, CourseSearchTask, TaskMode};
+use crate::webdriver::session::Session;
+use super::CourseSource;
+
+// Scrapes the live portal through the logged in WebDriver session, one course search at a time
+pub struct CusisSource {
+    driver: Arc<Mutex<WebDriver>>,
+    session: Arc<Session>
+}
+
+impl CusisSource {
+    pub fn new(driver: Arc<Mutex<WebDriver>>, session: Arc<Session>) -> CusisSource {
+        CusisSource { driver, session }
+    }
+}
+
+#[async_trait]
+impl CourseSource for CusisSource {
+    fn name(&self) -> String {
+        "CUSIS".to_string()
+    }
+
+    async fn fetch(&self, term: &str, course_names: &[String]) -> CusisResult<DashMap<String, Vec<Course>>> {
+        let course_collection: Arc<DashMap<String, Vec<Course>>> = Arc::new(DashMap::new());
+        if course_names.is_empty() {
+            return Ok(DashMap::new());
+        }
+        let (course_search_tx, course_search_rx) = channel::<CourseSearchTask>(10);
+        // Consumer thread: Process each search task one by one
+        let consumer_handle = tokio::spawn(
+            process_search_tasks(
+                course_search_rx,
+                self.driver.clone(),
+                course_collection.clone(),
+                TaskMode::Search,
+                self.session.clone(),
+                None
+            )
+        );
+        for course in course_names {
+            let task = CourseSearchTask{ course: course.clone(), term: term.to_string(), code: None };
+            if let Err(e) = course_search_tx.send(task).await {
+                eprintln!("Failed to send Course Search Task {}", e);
+            }
+        }
+        drop(course_search_tx);
+        match consumer_handle.await {
+            Ok(Ok(())) => {},
+            Ok(Err(e)) => {
+                // the courses found so far are only worth keeping while the session is still usable
+                if let CusisError::SessionExpired = e {
+                    return Err(e);
+                }
+                eprintln!("Course search stopped early: {}", e);
+            }
+            Err(e) => return Err(CusisError::DriverUnavailable(format!("the course search task stopped: {:?}", e)))
+        }
+        Ok(Arc::try_unwrap(course_collection).unwrap_or_else(|shared| (*shared).clone()))
+    }
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::path::PathBuf;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use crate::course::course_manager::Course;
+use crate::error::CusisResult;
+use crate::parser::cusis_page::parse_course_information;
+use super::CourseSource;
+
+// Replays "Course Information" pages saved from CUSIS, one <COURSE>.html per course, through the same parser
+// the live scraper uses. Handy for planning offline and for reproducing a parsing bug from a saved page.
+pub struct FixtureSource {
+    dir: PathBuf
+}
+
+impl FixtureSource {
+    pub fn new(dir: impl Into<PathBuf>) -> FixtureSource {
+        FixtureSource { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl CourseSource for FixtureSource {
+    fn name(&self) -> String {
+        format!("saved pages in {}", self.dir.display())
+    }
+
+    async fn fetch(&self, _term: &str, course_names: &[String]) -> CusisResult<DashMap<String, Vec<Course>>> {
+        let course_collection = DashMap::new();
+        for course_name in course_names {
+            let path = self.dir.join(format!("{}.html", course_name));
+            match fs::read_to_string(&path) {
+                Ok(html) => {
+                    let options = parse_course_information(&html, course_name).combinations();
+                    course_collection.insert(course_name.clone(), options);
+                }
+                Err(e) => eprintln!("No saved page for {} at {}: {}", course_name, path.display(), e)
+            }
+        }
+        Ok(course_collection)
+    }
+}
+
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::fs;
+use async_trait::async_trait;
+use chrono::NaiveTime;
+use dashmap::DashMap;
+use crate::course::course_manager::{Component, Course, Section};
+use crate::course::meeting::{Meeting, Weekday};
+use crate::error::{CusisError, CusisResult};
+use super::CourseSource;
+
+// Reads course options from a file exported by hand or by another tool. A ".json" file holds either a
+// list of courses or a map of course name to its courses in the catalog's format, anything else is read
+// as CSV with one meeting per line:
+// course,option,instructor,class_number,component,label,day,start,end,room
+// where lines sharing a course and option form one combination, e.g.
+// CSCI3100,1,Prof. Chan,8232,LEC,,Monday,14:30,16:15,Lady Shaw Bldg LT1
+// A field holding a comma goes in double quotes, with "" for a quote inside it.
+pub struct ImportSource {
+    path: String
+}
+
+impl ImportSource {
+    pub fn new(path: String) -> ImportSource {
+        ImportSource { path }
+    }
+}
+
+pub fn parse_json(data: &str) -> Result<HashMap<String, Vec<Course>>, String> {
+    if let Ok(courses) = serde_json::from_str::<HashMap<String, Vec<Course>>>(data) {
+        return Ok(courses);
+    }
+    let courses = serde_json::from_str::<Vec<Course>>(data).map_err(|e| e.to_string())?;
+    let mut by_name: HashMap<String, Vec<Course>> = HashMap::new();
+    for course in courses {
+        by_name.entry(course.course_name.clone()).or_default().push(course);
+    }
+    Ok(by_name)
+}
+
+fn parse_time(field: &str, line_number: usize) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(field, "%H:%M")
+        .map_err(|_| format!("line {}: invalid time \"{}\"", line_number + 1, field))
+}
+
+// the fields of one CSV line, commas inside double quotes do not split
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c)
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+pub fn parse_csv(data: &str) -> Result<HashMap<String, Vec<Course>>, String> {
+    // (course name, option) in the order they first appear
+    let mut option_order: Vec<(String, String)> = vec![];
+    let mut options: HashMap<(String, String), Course> = HashMap::new();
+    for (line_number, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("course,") {
+            continue;
+        }
+        let fields = split_fields(line);
+        let [course_name, option, instructor, class_number, component, label, day, start, end, room] = fields.as_slice() else {
+            return Err(format!("line {}: expected 10 fields, found {}", line_number + 1, fields.len()));
+        };
+        let class_number = class_number.parse::<u32>()
+            .map_err(|_| format!("line {}: invalid class number \"{}\"", line_number + 1, class_number))?;
+        let weekday = Weekday::parse(day)
+            .ok_or_else(|| format!("line {}: invalid day \"{}\"", line_number + 1, day))?;
+        let mut meeting = Meeting::new(weekday, parse_time(start, line_number)?, parse_time(end, line_number)?)
+            .ok_or_else(|| format!("line {}: a class must end after it starts", line_number + 1))?;
+        meeting.room = room.to_string();
+
+        let key = (course_name.to_string(), option.to_string());
+        if !options.contains_key(&key) {
+            option_order.push(key.clone());
+        }
+        let course = options.entry(key).or_insert_with(|| {
+            Course::create_course_time(course_name.to_string(), instructor.to_string(), vec![])
+        });
+        let section = match course.sections.iter_mut().position(|section| section.class_number == class_number) {
+            Some(index) => &mut course.sections[index],
+            None => {
+                course.sections.push(Section::new(Component::parse(component), class_number, label.to_string()));
+                course.sections.last_mut().unwrap()
+            }
+        };
+        section.meetings.push(meeting);
+    }
+
+    let mut by_name: HashMap<String, Vec<Course>> = HashMap::new();
+    for key in option_order {
+        let course = options.remove(&key).unwrap();
+        by_name.entry(key.0).or_default().push(course);
+    }
+    Ok(by_name)
+}
+
+#[async_trait]
+impl CourseSource for ImportSource {
+    fn name(&self) -> String {
+        format!("import file {}", self.path)
+    }
+
+    async fn fetch(&self, _term: &str, course_names: &[String]) -> CusisResult<DashMap<String, Vec<Course>>> {
+        let data = fs::read_to_string(&self.path)
+            .map_err(|e| CusisError::InvalidCourseData(format!("cannot read {}: {}", self.path, e)))?;
+        let mut courses = if self.path.to_lowercase().ends_with(".json") {
+            parse_json(&data)
+        }
+        else{
+            parse_csv(&data)
+        }.map_err(|e| CusisError::InvalidCourseData(format!("{}: {}", self.path, e)))?;
+
+        let course_collection = DashMap::new();
+        for course_name in course_names {
+            match courses.remove(course_name) {
+                Some(options) => { course_collection.insert(course_name.clone(), options); }
+                None => eprintln!("{} is not in {}", course_name, self.path)
+            }
+        }
+        Ok(course_collection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_groups_meetings_into_options() {
+        let data = "\
+course,option,instructor,class_number,component,label,day,start,end,room
+CSCI3100,1,Prof. Chan,8232,LEC,,Monday,14:30,16:15,Lady Shaw Bldg LT1
+CSCI3100,1,Prof. Chan,8232,LEC,,Thursday,10:30,11:15,Lady Shaw Bldg LT1
+CSCI3100,1,Prof. Chan,8810,TUT,T01,Tuesday,17:30,18:15,ERB 404
+CSCI3100,2,Prof. Chan,8232,LEC,,Monday,14:30,16:15,Lady Shaw Bldg LT1
+CSCI3100,2,Prof. Chan,8885,TUT,T02,Friday,09:30,10:15,
+CSCI3100,3,\"Chan, Tai Man\",8232,LEC,,Monday,14:30,16:15,\"Ho Sin-Hang Engg Bldg, Rm 1\"
+";
+        let courses = parse_csv(data).unwrap();
+        let options = &courses["CSCI3100"];
+        assert_eq!(options.len(), 3);
+        assert_eq!(options[0].class_numbers(), vec![8232, 8810]);
+        assert_eq!(options[0].sections[0].meetings.len(), 2);
+        assert_eq!(options[0].sections[1].component, Component::Tutorial);
+        assert_eq!(options[0].sections[1].label, "T01");
+        assert_eq!(options[1].sections[1].meetings[0].weekday, Weekday::Friday);
+        assert_eq!(options[1].sections[1].meetings[0].room, "");
+        assert_eq!(options[2].sections[0].meetings[0].room, "Ho Sin-Hang Engg Bldg, Rm 1");
+
+        assert!(parse_csv("CSCI3100,1,Prof. Chan,8232,LEC,,Someday,14:30,16:15,LSB").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use thirtyfour::WebDriver;
+use tokio::sync::Mutex;
+use crate::config;
+use crate::course::catalog::FreshnessPolicy;
+use crate::course::course_manager::Course;
+use crate::error::CusisResult;
+use crate::webdriver::session::Session;
+
+pub mod cusis;
+pub mod catalog;
+pub mod import;
+pub mod fixture;
+
+// Where the scheduler's course options come from, the GUI and CLI pick one and never drive WebDriver themselves
+#[async_trait]
+pub trait CourseSource: Send + Sync {
+    fn name(&self) -> String;
+    // options of every requested course for `term`, courses the source does not know are left out
+    async fn fetch(&self, term: &str, course_names: &[String]) -> CusisResult<DashMap<String, Vec<Course>>>;
+}
+
+// What a plan is built from, as the registration page and the command line offer it
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceChoice {
+    // the course catalog in front of the live portal
+    Cusis,
+    // the course catalog alone, every cached course is used however old it is
+    Catalog,
+    // a JSON or CSV file, see import::ImportSource
+    Import(String),
+    // a directory of saved "Course Information" pages, see fixture::FixtureSource
+    SavedPages(String)
+}
+
+impl SourceChoice {
+    // "CUSIS", "Course catalog", "Import file" or "Saved pages", `path` is only read by the last two
+    pub fn parse(name: &str, path: &str) -> Option<SourceChoice> {
+        match name.trim().to_lowercase().as_str() {
+            "cusis" => Some(SourceChoice::Cusis),
+            "course catalog" => Some(SourceChoice::Catalog),
+            "import file" => Some(SourceChoice::Import(path.trim().to_string())),
+            "saved pages" => Some(SourceChoice::SavedPages(path.trim().to_string())),
+            _ => None
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SourceChoice::Cusis => "CUSIS",
+            SourceChoice::Catalog => "Course catalog",
+            SourceChoice::Import(_) => "Import file",
+            SourceChoice::SavedPages(_) => "Saved pages"
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            SourceChoice::Import(path) | SourceChoice::SavedPages(path) => path,
+            _ => ""
+        }
+    }
+
+    // an import file or a fixture directory named in the environment, which the app starts with
+    pub fn from_env() -> Option<SourceChoice> {
+        if let Some(path) = config::course_import_file() {
+            return Some(SourceChoice::Import(path));
+        }
+        config::course_fixture_dir().map(SourceChoice::SavedPages)
+    }
+
+    // `cusis` is the signed in driver and session, without them CUSIS is left out and only the catalog is read
+    pub fn build(&self, freshness: FreshnessPolicy, cusis: Option<(Arc<Mutex<WebDriver>>, Arc<Session>)>) -> Arc<dyn CourseSource> {
+        match self {
+            SourceChoice::Cusis => {
+                let fallback = cusis.map(|(driver, session)| Arc::new(cusis::CusisSource::new(driver, session)) as Arc<dyn CourseSource>);
+                Arc::new(catalog::CatalogSource::new(fallback, freshness))
+            }
+            SourceChoice::Catalog => Arc::new(catalog::CatalogSource::new(None, freshness)),
+            SourceChoice::Import(path) => Arc::new(import::ImportSource::new(path.clone())),
+            SourceChoice::SavedPages(dir) => Arc::new(fixture::FixtureSource::new(dir.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_choice_round_trips_its_label() {
+        for choice in [SourceChoice::Cusis, SourceChoice::Catalog, SourceChoice::Import("courses.csv".to_string()), SourceChoice::SavedPages("pages".to_string())] {
+            assert_eq!(SourceChoice::parse(choice.label(), choice.path()), Some(choice.clone()));
+        }
+        assert_eq!(SourceChoice::parse("course catalog", "ignored.csv"), Some(SourceChoice::Catalog));
+        assert!(SourceChoice::parse("Moodle", "").is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use thirtyfour::prelude::*;
+use super::process_manager::find_executable;
+
+const BUNDLED_FIREFOX: &str = r"win64\130.0.1\firefox.exe";
+const MACOS_FIREFOX: &str = "/Applications/Firefox.app/Contents/MacOS/firefox";
+const CHROMIUM_NAMES: [&str; 4] = ["chromium", "chromium-browser", "google-chrome", "chrome"];
+const MACOS_CHROMIUM: [&str; 2] = [
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome"
+];
+
+// The browser CUSIS is driven through, together with the WebDriver server that controls it
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BrowserBackend {
+    Firefox,
+    Chromium
+}
+
+fn path_from_env(env_name: &str) -> Option<PathBuf> {
+    env::var_os(env_name).filter(|path| !path.is_empty()).map(PathBuf::from)
+}
+
+impl BrowserBackend {
+    pub fn parse(name: &str) -> Option<BrowserBackend> {
+        match name.trim().to_lowercase().as_str() {
+            "firefox" | "gecko" | "geckodriver" => Some(BrowserBackend::Firefox),
+            "chromium" | "chrome" | "chromedriver" => Some(BrowserBackend::Chromium),
+            _ => None
+        }
+    }
+
+    // Firefox when it or geckodriver is around, otherwise Chromium when chromedriver is installed
+    pub fn detect() -> BrowserBackend {
+        let firefox = BrowserBackend::Firefox;
+        let chromium = BrowserBackend::Chromium;
+        if firefox.find_driver().is_some() || firefox.browser_binary().is_some() {
+            firefox
+        }
+        else if chromium.find_driver().is_some() {
+            chromium
+        }
+        else{
+            firefox
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrowserBackend::Firefox => "Firefox",
+            BrowserBackend::Chromium => "Chromium"
+        }
+    }
+
+    pub fn driver_name(&self) -> &'static str {
+        match self {
+            BrowserBackend::Firefox => "geckodriver",
+            BrowserBackend::Chromium => "chromedriver"
+        }
+    }
+
+    // $GECKODRIVER_PATH or $CHROMEDRIVER_PATH, then a copy next to the executable's working directory, then PATH
+    fn find_driver(&self) -> Option<PathBuf> {
+        let env_name = match self {
+            BrowserBackend::Firefox => "GECKODRIVER_PATH",
+            BrowserBackend::Chromium => "CHROMEDRIVER_PATH"
+        };
+        if let Some(path) = path_from_env(env_name) {
+            return Some(path);
+        }
+        let local = if cfg!(windows) {
+            format!("./{}.exe", self.driver_name())
+        }
+        else{
+            format!("./{}", self.driver_name())
+        };
+        if Path::new(&local).is_file() {
+            return Some(PathBuf::from(local));
+        }
+        find_executable(self.driver_name())
+    }
+
+    // a bare name still lets the OS report a readable "not found" when spawning
+    pub fn driver_path(&self) -> PathBuf {
+        self.find_driver().unwrap_or_else(|| PathBuf::from(self.driver_name()))
+    }
+
+    // $FIREFOX_BINARY or $CHROMIUM_BINARY, then the usual install locations. None leaves it to the driver,
+    // which knows the default install locations of every platform
+    pub fn browser_binary(&self) -> Option<PathBuf> {
+        match self {
+            BrowserBackend::Firefox => {
+                if let Some(path) = path_from_env("FIREFOX_BINARY") {
+                    return Some(path);
+                }
+                if cfg!(windows) && Path::new(BUNDLED_FIREFOX).is_file() {
+                    return Some(PathBuf::from(BUNDLED_FIREFOX));
+                }
+                find_executable("firefox").or_else(|| {
+                    Some(PathBuf::from(MACOS_FIREFOX)).filter(|path| cfg!(target_os = "macos") && path.is_file())
+                })
+            }
+            BrowserBackend::Chromium => {
+                if let Some(path) = path_from_env("CHROMIUM_BINARY") {
+                    return Some(path);
+                }
+                CHROMIUM_NAMES.iter().find_map(|name| find_executable(name)).or_else(|| {
+                    MACOS_CHROMIUM.iter().map(PathBuf::from).find(|path| cfg!(target_os = "macos") && path.is_file())
+                })
+            }
+        }
+    }
+
+    // opens a session on the driver listening on `port`
+    pub async fn connect(&self, port: &str, headless: bool) -> WebDriverResult<WebDriver> {
+        let server_url = format!("http://127.0.0.1:{}", port);
+        let binary = self.browser_binary().map(|path| path.to_string_lossy().to_string());
+        match self {
+            BrowserBackend::Firefox => {
+                let mut caps = DesiredCapabilities::firefox();
+                if headless {
+                    caps.set_headless()?;
+                }
+                if let Some(binary) = &binary {
+                    caps.set_firefox_binary(binary)?;
+                }
+                WebDriver::new(&server_url, caps).await
+            }
+            BrowserBackend::Chromium => {
+                let mut caps = DesiredCapabilities::chrome();
+                if headless {
+                    caps.set_headless()?;
+                }
+                if let Some(binary) = &binary {
+                    caps.set_binary(binary)?;
+                }
+                WebDriver::new(&server_url, caps).await
+            }
+        }
+    }
+}
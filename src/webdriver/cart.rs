@@ -0,0 +1,406 @@
+use std::fs;
+use std::sync::Arc;
+use chrono::Local;
+use dashmap::DashMap;
+use thirtyfour::prelude::*;
+use tokio::sync::Mutex;
+use tokio::sync::mpsc::{channel, unbounded_channel};
+use crate::config;
+use crate::error::{CusisError, CusisResult};
+use crate::parser::cusis_page::{parse_shopping_cart, CartEntry};
+use super::scrape::{process_search_tasks, ClassReview, CourseSearchTask, TaskMode};
+use super::screenshot;
+use super::session::{self, Session};
+use super::util::{search_and_click_element_with_retries, wait_til_title};
+
+// What the timetable page can do with the shopping cart for the schedule on display, every action but Check
+// changes the cart and only runs for real once its dry run has been confirmed
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CartAction {
+    Check,
+    AddMissing,
+    RemoveUnwanted,
+    SwapClasses
+}
+
+impl CartAction {
+    pub fn parse(name: &str) -> Option<CartAction> {
+        match name.trim().to_lowercase().as_str() {
+            "check" => Some(CartAction::Check),
+            "add" => Some(CartAction::AddMissing),
+            "remove" => Some(CartAction::RemoveUnwanted),
+            "swap" => Some(CartAction::SwapClasses),
+            _ => None
+        }
+    }
+}
+
+// How the shopping cart differs from a schedule, the (course, class numbers) pairs of a candidate
+#[derive(Debug, Default, PartialEq)]
+pub struct CartPlan {
+    pub kept: Vec<String>,
+    // courses of the schedule that are not in the cart at all
+    pub missing: Vec<(String, Vec<String>)>,
+    // picks of courses the schedule does not take, usually left over from an earlier schedule
+    pub unwanted: Vec<CartEntry>,
+    // a course in the cart with other classes than the schedule's, typically another tutorial, with the classes wanted
+    pub swaps: Vec<(CartEntry, Vec<String>)>
+}
+
+fn same_classes(entry: &CartEntry, codes: &[String]) -> bool {
+    let mut wanted: Vec<u32> = codes.iter().filter_map(|code| code.parse().ok()).collect();
+    let mut in_cart = entry.class_numbers.clone();
+    wanted.sort();
+    in_cart.sort();
+    wanted == in_cart
+}
+
+pub fn reconcile(cart: &[CartEntry], schedule: &[(String, Vec<String>)]) -> CartPlan {
+    let mut plan = CartPlan::default();
+    for entry in cart {
+        match schedule.iter().find(|(course, _)| *course == entry.course) {
+            Some((course, codes)) if same_classes(entry, codes) => plan.kept.push(course.clone()),
+            Some((_, codes)) => plan.swaps.push((entry.clone(), codes.clone())),
+            None => plan.unwanted.push(entry.clone())
+        }
+    }
+    for (course, codes) in schedule {
+        if !cart.iter().any(|entry| entry.course == *course) {
+            plan.missing.push((course.clone(), codes.clone()));
+        }
+    }
+    plan
+}
+
+fn class_list(class_numbers: impl IntoIterator<Item = impl ToString>) -> String {
+    class_numbers.into_iter().map(|number| number.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+impl CartPlan {
+    pub fn summary(&self) -> String {
+        let mut lines = vec![];
+        if !self.kept.is_empty() {
+            lines.push(format!("Already in the cart: {}", self.kept.join(", ")));
+        }
+        for (course, codes) in &self.missing {
+            lines.push(format!("Missing: {} ({})", course, class_list(codes)));
+        }
+        for entry in &self.unwanted {
+            lines.push(format!("Not in this schedule: {} ({})", entry.course, class_list(&entry.class_numbers)));
+        }
+        for (entry, codes) in &self.swaps {
+            lines.push(format!("Other classes: {} has {} in the cart, this schedule takes {}", entry.course, class_list(&entry.class_numbers), class_list(codes)));
+        }
+        if self.missing.is_empty() && self.unwanted.is_empty() && self.swaps.is_empty() {
+            lines.push("The shopping cart matches this schedule".to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+// How a cart action went: the outcome of every course that was tried and, when the pipeline stopped before
+// reaching the rest, why it stopped
+pub struct CartReport {
+    // what happened to a course that went through, e.g. "added to the shopping cart"
+    pub action: &'static str,
+    pub outcomes: Vec<(String, CusisResult<Option<ClassReview>>)>,
+    pub stopped: Option<CusisError>,
+    // picks a swap took out of the cart without getting the new classes in, the user has to add them back
+    pub lost: Vec<CartEntry>
+}
+
+impl CartReport {
+    // one line per course for the timetable page
+    pub fn summary(&self) -> String {
+        let mut lines: Vec<String> = self.outcomes.iter()
+            .map(|(course, outcome)| match outcome {
+                Ok(Some(review)) => match &review.screenshot {
+                    Some(path) => format!("{}: would submit {} (see {})", course, review.classes.join("; "), path.display()),
+                    None => format!("{}: would submit {}", course, review.classes.join("; "))
+                },
+                Ok(None) => format!("{}: {}", course, self.action),
+                Err(e) => format!("{}: failed, {}", course, e)
+            })
+            .collect();
+        if let Some(e) = &self.stopped {
+            lines.push(format!("Stopped before the remaining courses: {}", e));
+        }
+        for entry in &self.lost {
+            lines.push(format!("{} lost its old classes {}, add them back to the shopping cart", entry.course, class_list(&entry.class_numbers)));
+        }
+        lines.join("\n")
+    }
+}
+
+// What apply() has to show the user, and whether it was a dry run that waits for a confirmation
+pub struct CartOutcome {
+    pub summary: String,
+    pub needs_confirmation: bool
+}
+
+impl CartOutcome {
+    fn done(summary: String) -> CartOutcome {
+        CartOutcome { summary, needs_confirmation: false }
+    }
+
+    // a confirmation is only offered when the real run would change something
+    fn dry_run(verb: &str, summary: String, needs_confirmation: bool) -> CartOutcome {
+        let summary = format!("Dry run, nothing was changed. Confirm to {}:\n{}", verb, summary);
+        save_dry_run_report(&summary);
+        CartOutcome { summary, needs_confirmation }
+    }
+}
+
+// Opens the shopping cart of `term`, signing back in first when the session has expired
+async fn open_cart(driver: &WebDriver, session: &Session, term: &str) -> CusisResult<()> {
+    driver.goto(config::cusis_shopping_cart_link()).await?;
+    if session::is_session_expired(driver).await {
+        session.restore(driver).await?;
+        driver.goto(config::cusis_shopping_cart_link()).await?;
+    }
+    // CUSIS asks for the term first when there are carts of several terms
+    if let Ok(term_link) = driver.find(By::XPath(format!("//a[text()='{}']", term))).await {
+        term_link.click().await?;
+    }
+    wait_til_title(driver, "Shopping Cart").await
+}
+
+// the classes already in the shopping cart of `term`
+pub async fn read_cart(driver: &WebDriver, session: &Session, term: &str) -> CusisResult<Vec<CartEntry>> {
+    open_cart(driver, session, term).await?;
+    Ok(parse_shopping_cart(&driver.source().await?))
+}
+
+// Feeds `schedule`, the (course, class numbers) pairs of a candidate, through the enroll pipeline one course at a time,
+// `mode` is TaskMode::DryRun to only review what would be submitted
+pub async fn add_schedule(
+    driver: Arc<Mutex<WebDriver>>,
+    session: Arc<Session>,
+    term: &str,
+    schedule: Vec<(String, Vec<String>)>,
+    mode: TaskMode
+) -> CartReport {
+    let (task_tx, task_rx) = channel::<CourseSearchTask>(10);
+    let (outcome_tx, mut outcome_rx) = unbounded_channel();
+    let consumer_handle = tokio::spawn(
+        process_search_tasks(
+            task_rx,
+            driver.clone(),
+            Arc::new(DashMap::new()),
+            mode,
+            session,
+            Some(outcome_tx)
+        )
+    );
+    for (course, codes) in schedule {
+        let task = CourseSearchTask{ course, term: term.to_string(), code: Some(codes) };
+        if let Err(e) = task_tx.send(task).await {
+            eprintln!("Failed to send Course Search Task {}", e);
+        }
+    }
+    drop(task_tx);
+
+    let stopped = match consumer_handle.await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e),
+        Err(e) => Some(CusisError::DriverUnavailable(format!("the enroll task stopped: {:?}", e)))
+    };
+    let mut outcomes = vec![];
+    while let Ok(outcome) = outcome_rx.try_recv() {
+        outcomes.push(outcome);
+    }
+    CartReport { action: "added to the shopping cart", outcomes, stopped, lost: vec![] }
+}
+
+// Ticks the rows of `entries` on the open cart page and deletes them in one go
+async fn remove_entries(driver: &WebDriver, entries: &[CartEntry]) -> CusisResult<Vec<(String, CusisResult<Option<ClassReview>>)>> {
+    let mut outcomes = vec![];
+    let mut ticked = vec![];
+    for entry in entries {
+        let row_checkbox = By::XPath(format!("//tr[.//*[contains(text(), '( {} )')]]//input[@type='checkbox']", entry.class_numbers[0]));
+        match driver.find(row_checkbox).await {
+            Ok(checkbox) => {
+                checkbox.click().await?;
+                ticked.push(entry);
+            }
+            Err(_) => outcomes.push((entry.course.clone(), Err(CusisError::PageLayoutChanged(format!("the cart row of {}", entry.course)))))
+        }
+    }
+    if ticked.is_empty() {
+        return Ok(outcomes);
+    }
+    search_and_click_element_with_retries(driver, By::XPath("//*[text()='Delete']"), 10, &Option::None).await?;
+    search_and_click_element_with_retries(driver, By::XPath("//*[text()='Yes']"), 10, &Option::None).await?;
+    wait_til_title(driver, "Shopping Cart").await?;
+    // a row that is still listed was not deleted, whatever the page said
+    let remaining = parse_shopping_cart(&driver.source().await?);
+    for entry in ticked {
+        let outcome = if remaining.contains(entry) {
+            Err(CusisError::PageLayoutChanged(format!("a Delete button that removes {}", entry.course)))
+        }
+        else{
+            Ok(None)
+        };
+        outcomes.push((entry.course.clone(), outcome));
+    }
+    Ok(outcomes)
+}
+
+// keeps the dry run report next to its review screenshots
+fn save_dry_run_report(summary: &str) {
+    let dir = config::screenshot_dir();
+    let path = dir.join(format!("{}-dry-run.txt", Local::now().format("%Y%m%d-%H%M%S")));
+    if let Err(e) = fs::create_dir_all(&dir).and_then(|()| fs::write(&path, summary)) {
+        eprintln!("Failed to save the dry run report to {}: {}", path.display(), e);
+    }
+}
+
+// a dry run through the enroll pipeline, it stops at "Review Class Selection" for every course
+async fn review_schedule(
+    driver: Arc<Mutex<WebDriver>>,
+    session: Arc<Session>,
+    term: &str,
+    schedule: Vec<(String, Vec<String>)>,
+    verb: &str
+) -> CartOutcome {
+    let report = add_schedule(driver, session, term, schedule, TaskMode::DryRun).await;
+    let reviewed = report.stopped.is_none() && report.outcomes.iter().any(|(_, outcome)| outcome.is_ok());
+    CartOutcome::dry_run(verb, report.summary(), reviewed)
+}
+
+// Reads the cart of `term`, compares it with `schedule` and carries out `action`, returning what to show the user.
+// Unless `confirmed`, an action that changes the cart is only dry run
+pub async fn apply(
+    driver: Arc<Mutex<WebDriver>>,
+    session: Arc<Session>,
+    term: &str,
+    schedule: Vec<(String, Vec<String>)>,
+    action: CartAction,
+    confirmed: bool
+) -> CusisResult<CartOutcome> {
+    let plan = {
+        let driver_lock = driver.lock().await;
+        let cart = screenshot::on_error(&driver_lock, "read-cart", read_cart(&driver_lock, &session, term).await).await?;
+        reconcile(&cart, &schedule)
+    };
+    let report = match action {
+        CartAction::Check => return Ok(CartOutcome::done(plan.summary())),
+        CartAction::AddMissing => {
+            if plan.missing.is_empty() {
+                return Ok(CartOutcome::done(format!("Nothing to add\n{}", plan.summary())));
+            }
+            if !confirmed {
+                return Ok(review_schedule(driver, session, term, plan.missing, "add").await);
+            }
+            add_schedule(driver, session, term, plan.missing, TaskMode::Enroll).await
+        }
+        CartAction::RemoveUnwanted => {
+            if plan.unwanted.is_empty() {
+                return Ok(CartOutcome::done(format!("Nothing to remove\n{}", plan.summary())));
+            }
+            if !confirmed {
+                let lines: Vec<String> = plan.unwanted.iter()
+                    .map(|entry| format!("{}: would remove {}", entry.course, class_list(&entry.class_numbers)))
+                    .collect();
+                return Ok(CartOutcome::dry_run("remove", lines.join("\n"), true));
+            }
+            let driver_lock = driver.lock().await;
+            let outcomes = screenshot::on_error(&driver_lock, "remove-from-cart", remove_entries(&driver_lock, &plan.unwanted).await).await?;
+            CartReport { action: "removed from the shopping cart", outcomes, stopped: None, lost: vec![] }
+        }
+        // the old pick has to leave the cart before CUSIS takes the course again, so the new classes are dry run
+        // first and only the courses that made it to the review lose their old pick
+        CartAction::SwapClasses => {
+            if plan.swaps.is_empty() {
+                return Ok(CartOutcome::done(format!("Nothing to swap\n{}", plan.summary())));
+            }
+            let new_picks: Vec<(String, Vec<String>)> = plan.swaps.iter().map(|(entry, codes)| (entry.course.clone(), codes.clone())).collect();
+            if !confirmed {
+                return Ok(review_schedule(driver, session, term, new_picks, "swap").await);
+            }
+            let review = add_schedule(driver.clone(), session.clone(), term, new_picks, TaskMode::DryRun).await;
+            let mut failed = vec![];
+            let mut entries = vec![];
+            for (course, outcome) in review.outcomes {
+                match outcome {
+                    Ok(_) => entries.extend(plan.swaps.iter().filter(|(entry, _)| entry.course == course).map(|(entry, _)| entry.clone())),
+                    Err(e) => failed.push((course, Err(e)))
+                }
+            }
+            if entries.is_empty() {
+                return Ok(CartOutcome::done(CartReport { action: "swapped in the shopping cart", outcomes: failed, stopped: review.stopped, lost: vec![] }.summary()));
+            }
+            let removed = {
+                let driver_lock = driver.lock().await;
+                let removed = match open_cart(&driver_lock, &session, term).await {
+                    Ok(()) => remove_entries(&driver_lock, &entries).await,
+                    Err(e) => Err(e)
+                };
+                screenshot::on_error(&driver_lock, "swap-in-cart", removed).await?
+            };
+            let mut to_add = vec![];
+            let mut taken_out = vec![];
+            for (course, outcome) in removed {
+                match outcome {
+                    Ok(_) => {
+                        if let Some((entry, codes)) = plan.swaps.iter().find(|(entry, _)| entry.course == course) {
+                            to_add.push((course, codes.clone()));
+                            taken_out.push(entry.clone());
+                        }
+                    }
+                    Err(e) => failed.push((course, Err(e)))
+                }
+            }
+            let mut report = add_schedule(driver, session, term, to_add, TaskMode::Enroll).await;
+            report.action = "swapped in the shopping cart";
+            report.lost = taken_out.into_iter()
+                .filter(|entry| !report.outcomes.iter().any(|(course, outcome)| *course == entry.course && outcome.is_ok()))
+                .collect();
+            report.outcomes.extend(failed);
+            if report.stopped.is_none() {
+                report.stopped = review.stopped;
+            }
+            report
+        }
+    };
+    Ok(CartOutcome::done(report.summary()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconcile_sorts_the_cart_against_the_schedule() {
+        let cart = vec![
+            CartEntry { course: "CSCI3180".to_string(), class_numbers: vec![8232, 8885] },
+            CartEntry { course: "CSCI3100".to_string(), class_numbers: vec![8249, 8853] },
+            CartEntry { course: "ENGG1110".to_string(), class_numbers: vec![5521] }
+        ];
+        let schedule = vec![
+            ("CSCI3180".to_string(), vec!["8232".to_string(), "8810".to_string()]),
+            ("CSCI3100".to_string(), vec!["8249".to_string(), "8853".to_string()]),
+            ("UGFH1000".to_string(), vec!["6120".to_string()])
+        ];
+        let plan = reconcile(&cart, &schedule);
+        assert_eq!(plan.kept, vec!["CSCI3100".to_string()]);
+        assert_eq!(plan.missing, vec![("UGFH1000".to_string(), vec!["6120".to_string()])]);
+        assert_eq!(plan.unwanted, vec![cart[2].clone()]);
+        assert_eq!(plan.swaps, vec![(cart[0].clone(), vec!["8232".to_string(), "8810".to_string()])]);
+        assert_eq!(reconcile(&cart[1..2], &schedule[1..2]).summary(), "Already in the cart: CSCI3100\nThe shopping cart matches this schedule");
+    }
+
+    #[test]
+    fn test_failed_swap_names_the_classes_to_add_back() {
+        let report = CartReport {
+            action: "swapped in the shopping cart",
+            outcomes: vec![("CSCI3180".to_string(), Err(CusisError::EnrollmentRejected("Class full".to_string())))],
+            stopped: None,
+            lost: vec![CartEntry { course: "CSCI3180".to_string(), class_numbers: vec![8232, 8885] }]
+        };
+        assert_eq!(
+            report.summary(),
+            "CSCI3180: failed, CUSIS did not take the classes: Class full\nCSCI3180 lost its old classes 8232 8885, add them back to the shopping cart"
+        );
+    }
+}
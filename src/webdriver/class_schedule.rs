@@ -0,0 +1,25 @@
+use thirtyfour::prelude::*;
+use tokio::time::{self, Duration};
+use crate::config;
+use crate::course::course_manager::Course;
+use crate::error::CusisResult;
+use crate::parser::cusis_page::parse_enrolled_classes;
+use super::session::{self, Session};
+use super::util::{search_and_click_element_with_retries, wait_til_title};
+
+// Reads the courses the student is already enrolled in for `term` off "My Class Schedule"
+pub async fn read_enrolled_classes(driver: &WebDriver, session: &Session, term: &str) -> CusisResult<Vec<Course>> {
+    driver.goto(config::cusis_class_schedule_link()).await?;
+    if session::is_session_expired(driver).await {
+        session.restore(driver).await?;
+        driver.goto(config::cusis_class_schedule_link()).await?;
+    }
+    // the term is asked for first when the student has classes in several terms
+    if let Ok(term_radio) = driver.find(By::XPath(format!("//tr[.//*[text()='{}']]//input[@type='radio']", term))).await {
+        term_radio.click().await?;
+        search_and_click_element_with_retries(driver, By::XPath("//*[text()='Continue']"), 10, &Option::None).await?;
+        time::sleep(Duration::from_secs(2)).await;
+    }
+    wait_til_title(driver, "My Class Schedule").await?;
+    Ok(parse_enrolled_classes(&driver.source().await?))
+}
\ No newline at end of file
@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::PathBuf;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha256;
+use thirtyfour::Cookie;
+use crate::config;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+// OWASP's recommendation for PBKDF2-HMAC-SHA256
+#[cfg(not(test))]
+const PBKDF2_ROUNDS: u32 = 600_000;
+// a debug build takes seconds for each full derivation, the tests only need the round trip
+#[cfg(test)]
+const PBKDF2_ROUNDS: u32 = 1_000;
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+// salt, nonce and then the AES-256-GCM ciphertext, a fresh salt and nonce are drawn for every save
+pub fn encrypt(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext).map_err(|e| e.to_string())?;
+
+    let mut data = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+// None for a wrong passphrase as well as for a truncated or tampered file
+pub fn decrypt(passphrase: &str, data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return None;
+    }
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let key = derive_key(passphrase, salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()
+}
+
+// The CUSIS session cookies of the last run, encrypted on disk with a key derived from a local passphrase
+pub struct CookieStore {
+    path: PathBuf,
+    passphrase: String
+}
+
+impl CookieStore {
+    pub fn new(path: impl Into<PathBuf>, passphrase: String) -> CookieStore {
+        CookieStore { path: path.into(), passphrase }
+    }
+
+    // cookies are only kept across runs once $CUSIS_SESSION_PASSPHRASE is set
+    pub fn from_env() -> Option<CookieStore> {
+        Some(CookieStore::new(config::session_file(), config::session_passphrase()?))
+    }
+
+    pub fn save(&self, cookies: &[Cookie]) -> Result<(), String> {
+        let json = serde_json::to_vec(cookies).map_err(|e| e.to_string())?;
+        let data = encrypt(&self.passphrase, &json)?;
+        fs::write(&self.path, data).map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+
+    pub fn load(&self) -> Option<Vec<Cookie>> {
+        let data = fs::read(&self.path).ok()?;
+        match decrypt(&self.passphrase, &data) {
+            Some(json) => serde_json::from_slice(&json).ok(),
+            None => {
+                eprintln!("Cannot decrypt {}, the passphrase may have changed", self.path.display());
+                None
+            }
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.is_file()
+    }
+
+    pub fn clear(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookies_only_decrypt_with_the_same_passphrase() {
+        let data = encrypt("correct horse", b"[{\"name\":\"PS_TOKEN\"}]").unwrap();
+        assert!(!data.windows(8).any(|window| window == b"PS_TOKEN"));
+        assert_eq!(decrypt("correct horse", &data).unwrap(), b"[{\"name\":\"PS_TOKEN\"}]");
+        assert!(decrypt("battery staple", &data).is_none());
+        assert!(decrypt("correct horse", &data[..20]).is_none());
+    }
+}
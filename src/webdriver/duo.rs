@@ -0,0 +1,119 @@
+use thirtyfour::prelude::*;
+use tokio::time::{self, Duration};
+use crate::error::{CusisError, CusisResult};
+
+// Selectors of Duo's prompt, kept together since Duo reworks its page far more often than CUSIS does
+const OTHER_OPTIONS: &str = ".other-options-link";
+// one row per way of confirming, e.g. "Duo Push iPhone" or "Phone call +XX XXXX 1234"
+const METHOD_ROWS: &str = "[data-testid^='test-id-']";
+const PASSCODE_INPUT: &str = "#passcode-input";
+const VERIFY_BUTTON: &str = ".verify-button";
+const TRY_AGAIN: &str = ".try-again-button";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DuoMethod {
+    Push,
+    Call,
+    Passcode
+}
+
+impl DuoMethod {
+    // from the data-testid of a method row, None for methods the app cannot drive such as security keys
+    fn from_test_id(test_id: &str) -> Option<DuoMethod> {
+        let test_id = test_id.to_lowercase();
+        if test_id.contains("push") {
+            Some(DuoMethod::Push)
+        }
+        else if test_id.contains("phone") || test_id.contains("call") {
+            Some(DuoMethod::Call)
+        }
+        else if test_id.contains("otp") || test_id.contains("passcode") {
+            Some(DuoMethod::Passcode)
+        }
+        else{
+            None
+        }
+    }
+}
+
+// One way of confirming the login that Duo offers, together with the device it goes to
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuoOption {
+    pub method: DuoMethod,
+    pub label: String
+}
+
+// the method rows with the method each one stands for, opening the full list first when Duo shows only its default
+async fn method_rows(driver: &WebDriver) -> CusisResult<Vec<(WebElement, DuoMethod)>> {
+    if let Ok(link) = driver.find(By::Css(OTHER_OPTIONS)).await {
+        if link.is_clickable().await.unwrap_or(false) {
+            link.click().await?;
+            time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+    let mut rows = vec![];
+    for row in driver.find_all(By::Css(METHOD_ROWS)).await? {
+        let test_id = row.attr("data-testid").await?.unwrap_or_default();
+        if let Some(method) = DuoMethod::from_test_id(&test_id) {
+            rows.push((row, method));
+        }
+    }
+    Ok(rows)
+}
+
+// every option Duo offers on its current page, in the order it lists them
+pub async fn list_options(driver: &WebDriver) -> CusisResult<Vec<DuoOption>> {
+    let mut options = vec![];
+    for (row, method) in method_rows(driver).await? {
+        let label = row.text().await?.split_whitespace().collect::<Vec<&str>>().join(" ");
+        options.push(DuoOption { method, label });
+    }
+    Ok(options)
+}
+
+// Sends the `index`th option of list_options(), typing `passcode` for a passcode option
+pub async fn request(driver: &WebDriver, index: usize, passcode: &str) -> CusisResult<DuoMethod> {
+    // a request that ran out leaves Duo on its "try again" page
+    if let Ok(button) = driver.find(By::Css(TRY_AGAIN)).await {
+        button.click().await?;
+        time::sleep(Duration::from_millis(500)).await;
+    }
+    let mut rows = method_rows(driver).await?;
+    if index >= rows.len() {
+        return Err(CusisError::PageLayoutChanged(format!("Duo option {}", index + 1)));
+    }
+    let (row, method) = rows.swap_remove(index);
+    row.click().await?;
+    if method == DuoMethod::Passcode {
+        let input = driver.find(By::Css(PASSCODE_INPUT)).await
+            .map_err(|_| CusisError::PageLayoutChanged("the Duo passcode field".to_string()))?;
+        input.clear().await?;
+        input.send_keys(passcode.trim()).await?;
+        driver.find(By::Css(VERIFY_BUTTON)).await
+            .map_err(|_| CusisError::PageLayoutChanged("the Duo verify button".to_string()))?
+            .click().await?;
+    }
+    Ok(method)
+}
+
+// what the 2FA page says once `method` has been sent
+pub fn waiting_status(method: DuoMethod) -> &'static str {
+    match method {
+        DuoMethod::Push => "Waiting for you to approve the push on your phone...",
+        DuoMethod::Call => "Calling your phone, answer it and follow the instructions...",
+        DuoMethod::Passcode => "Checking the passcode..."
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_methods_are_read_from_the_row_test_ids() {
+        assert_eq!(DuoMethod::from_test_id("test-id-push"), Some(DuoMethod::Push));
+        assert_eq!(DuoMethod::from_test_id("test-id-phone"), Some(DuoMethod::Call));
+        assert_eq!(DuoMethod::from_test_id("test-id-mobile-otp"), Some(DuoMethod::Passcode));
+        assert_eq!(DuoMethod::from_test_id("test-id-webauthn"), None);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+// A local stand-in for the CUSIS portal. It serves PeopleSoft-like pages with the element ids and
+// page titles the WebDriver code looks for, so the login, DUO, term selection and course search
+// flow and adding a class to the shopping cart can be driven without real credentials or a phone.
+//
+//   /login          "Sign In"                  userNameInput, passwordInput, submitButton
+//   /duo            "Duo Security"             .other-options-link, a row per method with data-testid test-id-push
+//                                              (two devices), test-id-phone and test-id-mobile-otp, #passcode-input
+//                                              and .verify-button
+//   /duo/push       "Duo Security"             dont-trust-browser-button
+//   /home           "Homepage"
+//   /class-search   "Class Search and Enroll"  DERIVED_SSR_FL_SSR_CSTRMPRV_GRP, DERIVED_SSR_FL_SSR_CSTRMCUR_GRP, PTS_KEYWORDS3, PTS_SRCH_BTN
+//   /search         "Class Search Results"     PTS_LIST_TITLE$0
+//   /course         "Course Information"       ps_grid-body
+//   /review         "Review Class Selection"   the clicked class row of /course, Next
+//   /enroll         the steps after it         ?step=accept "Class Preferences" Accept, ?step=accepted Next,
+//                                              ?step=submit Submit and Yes, ?step=result "Add to Shopping Cart
+//                                              Results" with the added classes and Return to Keyword Search Page
+//   /my-schedule    "My Class Schedule"        one enrolled course in the PSGROUPBOXWBO / CLASS_MTG_VW layout
+//   /cart           "Shopping Cart"            a ps_grid-row with a checkbox per class, Delete and Yes, ?removed= drops rows
+
+pub const MOCK_PREVIOUS_TERMS: [&str; 1] = ["2024-25 Term 2"];
+pub const MOCK_CURRENT_TERMS: [&str; 2] = ["2025-26 Term 1", "2025-26 Term 2"];
+// the mock rejects this password so the failed-login path can be exercised
+pub const MOCK_WRONG_PASSWORD: &str = "wrong-password";
+
+// every class row of a mock course, one entry per line of the row text
+static MOCK_COURSES: [(&str, &str, &[&[&str]]); 4] = [
+    ("CSCI3100", "Software Engineering", &[
+        &["( 8249 ) - - LEC", "Open", "( 8853 ) -T01 - TUT", "Tuesday", "12:30pm to 2:15pm", "Lady Shaw Bldg LT2", "Monday", "11:30am to 12:15pm", "Lady Shaw Bldg LT2", "Monday", "4:30pm to 5:15pm", "Mong Man Wai Bldg 710", "Dr. LAM Tak Kei", "Class Capacity 40", "Enrollment Total 32", "Wait List Total 0"],
+        &["( 8249 ) - - LEC", "Open", "( 8208 ) -T03 - TUT", "Tuesday", "12:30pm to 2:15pm", "Lady Shaw Bldg LT2", "Monday", "11:30am to 12:15pm", "Lady Shaw Bldg LT2", "Wednesday", "5:30pm to 6:15pm", "Esther Lee Bldg 405", "Dr. LAM Tak Kei", "Class Capacity 40", "Enrollment Total 38", "Wait List Total 0"]
+    ]),
+    ("CSCI3180", "Principles of Programming Languages", &[
+        &["( 8232 ) - - LEC", "Open", "( 8810 ) -T01 - TUT", "Monday", "2:30pm to 4:15pm", "Lady Shaw Bldg LT1", "Tuesday", "3:30pm to 4:15pm", "Mong Man Wai Bldg 702", "Professor Lauren Marcelyn PICK", "Class Capacity 60", "Enrollment Total 41", "Wait List Total 0"],
+        &["( 8232 ) - - LEC", "Wait List", "( 8885 ) -T02 - TUT", "Monday", "2:30pm to 4:15pm", "Lady Shaw Bldg LT1", "Wednesday", "4:30pm to 5:15pm", "Mong Man Wai Bldg 710", "Professor Lauren Marcelyn PICK", "Class Capacity 30", "Enrollment Total 30", "Wait List Total 3"]
+    ]),
+    ("ELTU3502", "English for Engineering Students", &[
+        &["( 4980 ) BC01 - CLW", "Open", "Monday", "12:30pm to 2:15pm", "Wu Ho Man Yuen Bldg 502", "Ms. LEUNG Kit Chi Ella", "Class Capacity 25", "Enrollment Total 20", "Wait List Total 0"],
+        &["( 9663 ) CC01 - CLW", "Open", "Thursday", "10:30am to 12:15pm", "Wu Ho Man Yuen Bldg 502", "Ms. LEUNG Kit Chi Ella", "Class Capacity 25", "Enrollment Total 24", "Wait List Total 0"]
+    ]),
+    ("UGEA2163", "Film, Culture and Society", &[
+        &["( 8255 ) - - LEC", "Open", "Friday", "9:30am to 11:15am", "Yasumoto Int'l Acad Park LT7", "Dr. LAU Po Hei", "Class Capacity 150", "Enrollment Total 120", "Wait List Total 0"]
+    ])
+];
+
+pub struct MockCusisServer {
+    pub addr: String,
+    handle: JoinHandle<()>
+}
+
+impl MockCusisServer {
+    // binds `addr` ("127.0.0.1:0" picks a free port) and serves requests until the server is dropped
+    pub async fn start(addr: &str) -> io::Result<MockCusisServer> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?.to_string();
+        let handle = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_connection(stream).await {
+                                eprintln!("Mock CUSIS failed to answer a request: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => eprintln!("Mock CUSIS failed to accept a connection: {}", e)
+                }
+            }
+        });
+        Ok(MockCusisServer { addr, handle })
+    }
+}
+
+impl Drop for MockCusisServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    form: HashMap<String, String>
+}
+
+// decodes application/x-www-form-urlencoded pairs, which is all the mock pages ever send
+fn parse_pairs(text: &str) -> HashMap<String, String> {
+    text.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (url_decode(key), url_decode(value)))
+        .collect()
+}
+
+fn url_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%')
+                }
+            }
+            byte => decoded.push(byte)
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+async fn read_request(stream: &mut TcpStream) -> io::Result<Option<Request>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let content_length = head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let body_end = buffer.len().min(header_end + content_length);
+    Ok(Some(Request {
+        method,
+        path: path.to_string(),
+        query: parse_pairs(query),
+        form: parse_pairs(&String::from_utf8_lossy(&buffer[header_end..body_end]))
+    }))
+}
+
+// every method of the Duo prompt leads to the same approval, the passcode is not checked
+static DUO_PAGE: &str = r#"<a class="other-options-link" href="/duo">Other options</a>
+<div data-testid="test-id-push" onclick="location.href='/duo/push'">Duo Push iPhone</div>
+<div data-testid="test-id-push" onclick="location.href='/duo/push'">Duo Push iPad</div>
+<div data-testid="test-id-phone" onclick="location.href='/duo/push'">Phone call +XX XXXX 1234</div>
+<div data-testid="test-id-mobile-otp">Duo Mobile passcode</div>
+<input id="passcode-input" type="text"><button class="verify-button" onclick="location.href='/duo/push'">Verify</button>"#;
+
+fn page(title: &str, body: &str) -> String {
+    format!("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body>\n{}\n</body>\n</html>\n", title, body)
+}
+
+fn login_page(message: &str) -> String {
+    page("Sign In", &format!(
+        r#"<form id="loginForm" method="post" action="/login">
+  <p id="errorText">{}</p>
+  <input id="userNameInput" name="UserName" type="email">
+  <input id="passwordInput" name="Password" type="password">
+  <span id="submitButton" role="button" onclick="document.getElementById('loginForm').submit()">Sign in</span>
+</form>"#,
+        message
+    ))
+}
+
+fn term_rows(terms: &[&str]) -> String {
+    terms.iter()
+        .map(|term| format!(r#"<tr onclick="selectTerm('{0}')"><td><a>{0}</a></td></tr>"#, term))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn class_search_page() -> String {
+    page("Class Search and Enroll", &format!(
+        r#"<script>
+  function selectTerm(term) {{ document.getElementById('selectedTerm').textContent = term; }}
+  function toggle(id) {{ var table = document.getElementById(id); table.style.display = table.style.display == 'none' ? '' : 'none'; }}
+  function search() {{ location.href = '/search?keyword=' + encodeURIComponent(document.getElementById('PTS_KEYWORDS3').value); }}
+</script>
+<p id="selectedTerm"></p>
+<a id="DERIVED_SSR_FL_SSR_CSTRMPRV_GRP" onclick="toggle('previousTerms')">Previous Terms</a>
+<table id="previousTerms" title="Previous Terms">
+{}
+</table>
+<a id="DERIVED_SSR_FL_SSR_CSTRMCUR_GRP" onclick="toggle('currentTerms')">Current Terms</a>
+<table id="currentTerms" title="Current Terms">
+{}
+</table>
+<input id="PTS_KEYWORDS3" type="text">
+<a id="PTS_SRCH_BTN" role="button" onclick="search()">Search</a>"#,
+        term_rows(&MOCK_PREVIOUS_TERMS),
+        term_rows(&MOCK_CURRENT_TERMS)
+    ))
+}
+
+fn find_course(keyword: &str) -> Option<&'static (&'static str, &'static str, &'static [&'static [&'static str]])> {
+    let keyword: String = keyword.chars().filter(|c| !c.is_whitespace()).collect();
+    MOCK_COURSES.iter().find(|(code, _, _)| code.eq_ignore_ascii_case(&keyword))
+}
+
+fn search_results_page(keyword: &str) -> String {
+    let body = match find_course(keyword) {
+        Some((code, title, _)) => format!(
+            r#"<ul class="ps_grid-list"><li class="ps_grid-row"><a class="ps-link" id="PTS_LIST_TITLE$0" href="/course?keyword={0}"><span>{1} {2} - {3}</span></a></li></ul>"#,
+            code, &code[..4], &code[4..], title
+        ),
+        None => "<p>No classes found</p>".to_string()
+    };
+    page("Class Search Results", &body)
+}
+
+fn course_information_page(keyword: &str) -> String {
+    let Some((code, title, rows)) = find_course(keyword) else {
+        return page("Course Information", "<p>Course not found</p>");
+    };
+    let rows: Vec<String> = rows.iter()
+        .enumerate()
+        .map(|(index, lines)| {
+            let cells: String = lines.iter().map(|line| format!("<td><span>{}</span></td>", line)).collect();
+            format!(r#"<tr class="ps_grid-row" onclick="location.href='/review?keyword={}&row={}'"><td><span>{}</span></td>{}</tr>"#, code, index, index + 1, cells)
+        })
+        .collect();
+    page("Course Information", &format!(
+        "<h2>{} {} - {}</h2>\n<table><tbody class=\"ps_grid-body\">\n{}\n</tbody></table>",
+        &code[..4], &code[4..], title, rows.join("\n")
+    ))
+}
+
+// the row of the course page that was clicked, Err holds the page body saying what was not found
+fn find_class(keyword: &str, row: &str) -> Result<(&'static str, &'static str, &'static [&'static str]), &'static str> {
+    let (code, title, rows) = find_course(keyword).ok_or("<p>Course not found</p>")?;
+    let lines = row.parse::<usize>().ok().and_then(|row| rows.get(row)).ok_or("<p>Class not found</p>")?;
+    Ok((code, title, lines))
+}
+
+// The pages a clicked class goes through on its way to the shopping cart, each button of ENROLL_BUTTONS links to
+// the next step: "review" Next, "accept" Accept, "accepted" Next, "submit" Submit and Yes, then "result"
+fn enroll_page(keyword: &str, row: &str, step: &str) -> String {
+    let page_title = match step {
+        "accept" | "accepted" => "Class Preferences",
+        "submit" => "Submit Class Selection",
+        "result" => "Add to Shopping Cart Results",
+        _ => "Review Class Selection"
+    };
+    let (code, title, lines) = match find_class(keyword, row) {
+        Ok(class) => class,
+        Err(body) => return page(page_title, body)
+    };
+    let button = |next_step: &str, label: &str| format!(r#"<a role="button" href="/enroll?keyword={}&row={}&step={}">{}</a>"#, code, row, next_step, label);
+    let heading = format!("<h2>{} {} - {}</h2>", &code[..4], &code[4..], title);
+    let body = match step {
+        "accept" => format!("{}\n<p>Wait list if class is full: No</p>\n{}", heading, button("accepted", "Accept")),
+        "accepted" => format!("{}\n<p>Wait list if class is full: No</p>\n{}", heading, button("submit", "Next")),
+        "submit" => format!(
+            "{}\n<a role=\"button\" onclick=\"document.getElementById('confirm').style.display = ''\">Submit</a>\n<div id=\"confirm\" style=\"display: none\"><p>Are you sure you want to submit?</p>{}</div>",
+            heading, button("result", "Yes")
+        ),
+        "result" => {
+            let classes: String = lines.iter()
+                .filter(|line| line.starts_with("( "))
+                .map(|class| format!("<span>{}</span><br>", class))
+                .collect();
+            format!(
+                "<table><tbody class=\"ps_grid-body\">\n<tr class=\"ps_grid-row\"><td><span>{} {} - {}</span></td><td>{}</td><td><span class=\"ps_box-value\">Success: This class has been added to your Shopping Cart.</span></td></tr>\n</tbody></table>\n<a role=\"button\" href=\"/class-search\">Return to Keyword Search Page</a>",
+                &code[..4], &code[4..], title, classes
+            )
+        }
+        // the header lines of the row, which is what Submit would send
+        _ => format!(
+            "{}\n<table><tbody class=\"ps_grid-body\">\n<tr class=\"ps_grid-row\"><td><span>{}</span></td></tr>\n</tbody></table>\n{}",
+            heading, lines.iter().take(3).cloned().collect::<Vec<&str>>().join("</span> <span>"), button("accept", "Next")
+        )
+    };
+    page(page_title, &body)
+}
+
+// the student is already enrolled in a course that none of the mock search results offer
+static MOCK_CLASS_SCHEDULE: &str = r#"<div class="PSGROUPBOXWBO">
+  <table><tr><td class="PAGROUPDIVIDER">UGFH 1000 - In Dialogue with Humanity</td></tr><tr><td><span id="STATUS$0">Enrolled</span></td></tr></table>
+  <table>
+    <tr id="trCLASS_MTG_VW$0_row1"><td><span id="DERIVED_CLS_DTL_CLASS_NBR$0">6120</span></td><td><a id="MTG_SECTION$0">-A</a></td><td><span id="MTG_COMP$0">Lecture</span></td><td><span id="MTG_SCHED$0">Th 2:30PM - 4:15PM</span></td><td><span id="MTG_LOC$0">Wu Ho Man Yuen Bldg 503</span></td><td><span id="DERIVED_CLS_DTL_SSR_INSTR_LONG$0">Dr. WONG Siu Ming</span></td></tr>
+  </table>
+</div>"#;
+
+// the cart starts with one course of the mock schedule and a stale pick, the rows deleted so far are kept in the URL
+static MOCK_CART: [(&str, &[&str]); 2] = [
+    ("CSCI 3100 - Software Engineering", &["( 8249 ) - - LEC", "( 8853 ) -T01 - TUT"]),
+    ("ENGG 1110 - Problem Solving By Programming", &["( 5521 ) - - LEC"])
+];
+
+// "8249" of "( 8249 ) - - LEC"
+fn class_number(class: &str) -> &str {
+    class.trim_start_matches("( ").split(' ').next().unwrap_or_default()
+}
+
+fn shopping_cart_page(removed: &str) -> String {
+    let rows: Vec<String> = MOCK_CART.iter()
+        .filter(|(_, classes)| !removed.split(',').any(|number| number == class_number(classes[0])))
+        .map(|(course, classes)| {
+            let class_number = class_number(classes[0]);
+            let classes: String = classes.iter().map(|class| format!("<span>{}</span><br>", class)).collect();
+            format!(r#"<tr class="ps_grid-row"><td><input type="checkbox" value="{}"></td><td><span>{}</span></td><td>{}</td></tr>"#, class_number, course, classes)
+        })
+        .collect();
+    page("Shopping Cart", &format!(
+        r#"<script>
+  function remove() {{
+    var removed = '{}'.split(',').filter(function (number) {{ return number != ''; }});
+    document.querySelectorAll('input[type=checkbox]:checked').forEach(function (box) {{ removed.push(box.value); }});
+    location.href = '/cart?removed=' + removed.join(',');
+  }}
+</script>
+<table><tbody class="ps_grid-body">
+{}
+</tbody></table>
+<a role="button" onclick="document.getElementById('confirm').style.display = ''">Delete</a>
+<div id="confirm" style="display: none"><a role="button" onclick="remove()">Yes</a></div>"#,
+        removed, rows.join("\n")
+    ))
+}
+
+fn route(request: &Request) -> (&'static str, Option<String>, String) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/login") => ("200 OK", None, login_page("")),
+        ("POST", "/login") => {
+            let user_name = request.form.get("UserName").cloned().unwrap_or_default();
+            let password = request.form.get("Password").cloned().unwrap_or_default();
+            if user_name.is_empty() || password.is_empty() || password == MOCK_WRONG_PASSWORD {
+                // the real portal stays on "Sign In" as well, which is how login() spots bad credentials
+                ("200 OK", None, login_page("Incorrect user ID or password."))
+            }
+            else{
+                ("303 See Other", Some("/duo".to_string()), String::new())
+            }
+        }
+        ("GET", "/duo") => ("200 OK", None, page("Duo Security", DUO_PAGE)),
+        ("GET", "/duo/push") => ("200 OK", None, page("Duo Security", r#"<p>Is this your device?</p><button id="dont-trust-browser-button" onclick="location.href='/home'">No, other people use this device</button>"#)),
+        ("GET", "/home") => ("200 OK", None, page("Homepage", "<p>Welcome to the mock CUSIS portal</p>")),
+        ("GET", "/class-search") => ("200 OK", None, class_search_page()),
+        ("GET", "/search") => ("200 OK", None, search_results_page(request.query.get("keyword").map(String::as_str).unwrap_or_default())),
+        ("GET", "/course") => ("200 OK", None, course_information_page(request.query.get("keyword").map(String::as_str).unwrap_or_default())),
+        ("GET", "/review") | ("GET", "/enroll") => ("200 OK", None, enroll_page(
+            request.query.get("keyword").map(String::as_str).unwrap_or_default(),
+            request.query.get("row").map(String::as_str).unwrap_or_default(),
+            request.query.get("step").map(String::as_str).unwrap_or("review")
+        )),
+        ("GET", "/my-schedule") => ("200 OK", None, page("My Class Schedule", MOCK_CLASS_SCHEDULE)),
+        ("GET", "/cart") => ("200 OK", None, shopping_cart_page(request.query.get("removed").map(String::as_str).unwrap_or_default())),
+        _ => ("404 Not Found", None, page("Not Found", "<p>Not Found</p>"))
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream) -> io::Result<()> {
+    let Some(request) = read_request(&mut stream).await? else {
+        return Ok(());
+    };
+    let (status, location, body) = route(&request);
+    let mut response = format!("HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    if let Some(location) = location {
+        response.push_str(&format!("Location: {}\r\n", location));
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cusis_page::{parse_class_review, parse_course_information, parse_enrolled_classes, parse_search_results, parse_shopping_cart, parse_submit_result, CartEntry};
+    use crate::webdriver::scrape::ENROLL_BUTTONS;
+    use scraper::{Html, Selector};
+
+    async fn send(server: &MockCusisServer, request: &str) -> String {
+        let mut stream = TcpStream::connect(&server.addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    // the link of the button labelled `label`, None when the button only works through a script
+    fn button_link(page: &str, label: &str) -> Option<String> {
+        let document = Html::parse_document(page);
+        let button = document.select(&Selector::parse("a[role=button]").unwrap())
+            .find(|button| button.text().collect::<String>().trim() == label)
+            .unwrap_or_else(|| panic!("no {} button on {}", label, page));
+        button.value().attr("href").map(String::from)
+    }
+
+    #[tokio::test]
+    async fn test_mock_portal_serves_the_scraped_pages() {
+        let server = MockCusisServer::start("127.0.0.1:0").await.unwrap();
+
+        let login = send(&server, "GET /login HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(login.starts_with("HTTP/1.1 200 OK") && login.contains("<title>Sign In</title>") && login.contains("id=\"userNameInput\""));
+        let body = "UserName=1155000000%40link.cuhk.edu.hk&Password=secret";
+        let accepted = send(&server, &format!("POST /login HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)).await;
+        assert!(accepted.starts_with("HTTP/1.1 303 See Other") && accepted.contains("Location: /duo"));
+        let body = format!("UserName=1155000000%40link.cuhk.edu.hk&Password={}", MOCK_WRONG_PASSWORD);
+        let rejected = send(&server, &format!("POST /login HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)).await;
+        assert!(rejected.contains("<title>Sign In</title>"));
+
+        let search = send(&server, "GET /class-search HTTP/1.1\r\n\r\n").await;
+        for id in ["DERIVED_SSR_FL_SSR_CSTRMPRV_GRP", "DERIVED_SSR_FL_SSR_CSTRMCUR_GRP", "PTS_KEYWORDS3", "PTS_SRCH_BTN"] {
+            assert!(search.contains(&format!("id=\"{}\"", id)), "{} is missing", id);
+        }
+
+        let results = send(&server, "GET /search?keyword=csci3180 HTTP/1.1\r\n\r\n").await;
+        let results = parse_search_results(&results);
+        assert_eq!(results[0].link.as_deref(), Some("/course?keyword=CSCI3180"));
+
+        let course = send(&server, "GET /course?keyword=CSCI3180 HTTP/1.1\r\n\r\n").await;
+        let offering = parse_course_information(&course, "CSCI3180");
+        assert_eq!(offering.combinations().len(), 2);
+
+        let review = send(&server, "GET /review?keyword=CSCI3100&row=1 HTTP/1.1\r\n\r\n").await;
+        assert_eq!(parse_class_review(&review), vec!["( 8249 ) - - LEC Open ( 8208 ) -T03 - TUT".to_string()]);
+
+        let schedule = send(&server, "GET /my-schedule HTTP/1.1\r\n\r\n").await;
+        assert_eq!(parse_enrolled_classes(&schedule)[0].class_numbers(), vec![6120]);
+
+        let cart = send(&server, "GET /cart?removed=5521 HTTP/1.1\r\n\r\n").await;
+        assert_eq!(parse_shopping_cart(&cart), vec![CartEntry { course: "CSCI3100".to_string(), class_numbers: vec![8249, 8853] }]);
+    }
+    #[tokio::test]
+    async fn test_mock_portal_adds_a_class_through_every_enroll_step() {
+        let server = MockCusisServer::start("127.0.0.1:0").await.unwrap();
+        let mut page = send(&server, "GET /review?keyword=CSCI3100&row=1 HTTP/1.1\r\n\r\n").await;
+        assert!(page.contains("<title>Review Class Selection</title>"));
+        // Submit only opens the confirmation, Yes on it is what leaves the page
+        for button in ENROLL_BUTTONS {
+            if let Some(link) = button_link(&page, button) {
+                page = send(&server, &format!("GET {} HTTP/1.1\r\n\r\n", link)).await;
+                assert!(page.starts_with("HTTP/1.1 200 OK"), "{} led to {}", button, page);
+            }
+        }
+        assert!(page.contains("<title>Add to Shopping Cart Results</title>"));
+        let result = parse_submit_result(&page);
+        assert!(result.succeeded);
+        assert_eq!(result.class_numbers, vec![8208, 8249]);
+        assert_eq!(button_link(&page, "Return to Keyword Search Page").as_deref(), Some("/class-search"));
+    }
+}
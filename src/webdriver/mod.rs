@@ -1,3 +1,11 @@
 pub mod scrape;
 pub mod util;
 pub mod process_manager;
+pub mod mock_server;
+pub mod browser;
+pub mod screenshot;
+pub mod session;
+pub mod cookie_store;
+pub mod duo;
+pub mod cart;
+pub mod class_schedule;
\ No newline at end of file
@@ -1,76 +1,64 @@
+use std::env;
+use std::path::PathBuf;
+use super::browser::BrowserBackend;
 use std::process::{Child, Command, Stdio};
 
-pub fn terminate_process(image_name: &str) -> Result<(), String> {
-    if cfg!(windows){
-        let result = Command::new("powershell")
-        .arg("taskkill")
-        .arg("/F")
-        .arg("/IM")
-        .arg(image_name)
-        .arg("/T")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn();
-        match result {
-            Ok(mut child) => {
-                let _ = child.wait();
-                Ok(())
-            },
-            Err(e) => Err(e.to_string())
-        }
+// the first `name` found on PATH, with its ".exe" suffix on Windows
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) && !name.ends_with(".exe") {
+        format!("{}.exe", name)
     }
     else{
-        let result  = Command::new("killall")
-        .arg(image_name)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn();
-
-        match result {
-            Ok(mut child) => {
-                let _ = child.wait();
-                Ok(())
-            },
-            Err(e) => Err(e.to_string())
-        }
-    }
-   
+        name.to_string()
+    };
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
 }
 
-pub fn spawn_process(os_flag: bool, args: &[&str]) -> Result<Child, String> {
-    let mut command = String::new();
-    match os_flag {
-        true => command.push_str("powershell"),
-        false => command.push_str("bash")
-    }
-    let result = Command::new(command)
-        .arg("-c")
-        .args(args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn();
-    match result {
-        Ok(child) => Ok(child),
-        Err(e) => Err(e.to_string())
-    }
+// runs the WebDriver server of `backend` directly rather than through a shell so a path with spaces works everywhere,
+// geckodriver and chromedriver both take "--port=N". Nothing reads the driver's log, a pipe would fill up and block it
+pub fn spawn_driver(backend: BrowserBackend, port: &str) -> Result<Child, String> {
+    let path = backend.driver_path();
+    Command::new(&path)
+        .arg(format!("--port={}", port))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("{}: {}", path.display(), e))
 }
 
-pub struct GeckodriverGuard(pub Child);
-impl Drop for GeckodriverGuard {
+// stops the driver this app started, and only that one, when the app exits
+pub struct DriverGuard(pub Child);
+impl Drop for DriverGuard {
     fn drop(&mut self) {
         let _ = self.0.kill();
+        let _ = self.0.wait();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_driver_guard_kills_only_its_own_process() {
+        let ours = Command::new("sleep").arg("30").spawn().unwrap();
+        let mut other = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = ours.id().to_string();
+        drop(DriverGuard(ours));
+        assert!(!Command::new("kill").args(["-0", &pid]).stderr(Stdio::null()).status().unwrap().success());
+        assert!(other.try_wait().unwrap().is_none());
+        other.kill().unwrap();
+        other.wait().unwrap();
+    }
+
+    #[cfg(unix)]
     #[test]
-    fn test_terminate_process() {
-        spawn_process(cfg!(windows), &["./geckodriver.exe", "--port=4444"]).unwrap();
-        let result: Result<_, String> = terminate_process("geckodriver.exe");
-        println!("res: {:?}", result);
-        assert!(result.is_ok() || result.is_err()); // Check that it returns a Result
+    fn test_find_executable_on_path() {
+        assert!(find_executable("sh").is_some_and(|path| path.ends_with("sh")));
+        assert!(find_executable("surely-not-an-installed-program").is_none());
     }
 }
 
@@ -1,12 +1,36 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 use super::util::*;
 use tokio::sync::Mutex;
+use tokio::sync::mpsc::UnboundedSender;
 use thirtyfour::prelude::*;
+use thirtyfour::WindowHandle;
 use std::collections::HashMap;
-use crate::CUSIS_COURSE_SEARCH_LINK;
+use crate::config;
+use crate::error::{CusisError, CusisResult};
 use dashmap::DashMap;
 use crate::course::course_manager::Course;
-use crate::cli::animation::Spinner;
+use crate::parser::cusis_page::{find_class_rows, parse_class_review, parse_search_results, parse_submit_result, SearchResult, CLASS_ROW_SELECTOR};
+use super::screenshot;
+use super::session::Session;
+
+// What a search task does once it reaches the "Course Information" page of its course
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TaskMode {
+    // store the class options of the course
+    Search,
+    // add the task's classes to the shopping cart
+    Enroll,
+    // stop at "Review Class Selection" and record what Enroll would submit
+    DryRun
+}
+
+// What the "Review Class Selection" page showed for a course during a dry run
+#[derive(Debug, Clone)]
+pub struct ClassReview {
+    pub classes: Vec<String>,
+    pub screenshot: Option<PathBuf>
+}
 
 #[derive(Clone)]
 pub struct CourseSearchTask{
@@ -22,7 +46,7 @@ pub async fn get_term_table(
     terms: &mut HashMap <String, (WebElement, String)>,
     selected_term: &Option<String>,
     error_flag: bool
-) -> Result<(), WebDriverError>{
+) -> CusisResult<()>{
 
     let title = match cond {
         "prev" => "[title=\"Previous Terms\"]",
@@ -52,13 +76,13 @@ pub async fn get_term_table(
                     },
                     None =>  {
                         if error_flag{
-                            return Err(WebDriverError::HttpError("Id cannot be found".to_string()));
+                            return Err(CusisError::PageLayoutChanged("the id of a term row".to_string()));
                         }
                     }
                 };
             }
         },
-        Err(err)=> {return  Err(err);}
+        Err(err)=> {return  Err(err.into());}
     };
     Ok(())
 }
@@ -74,160 +98,230 @@ pub async fn get_term_table(
 //     None
 // }
 
-pub async fn process_search_tasks(
-    mut rx: tokio::sync::mpsc::Receiver<CourseSearchTask>,
-    driver: Arc<Mutex<WebDriver>>,
-    mut course_collection: Arc<DashMap<String, Vec<Course>>>,
-    enroll: bool
-)-> WebDriverResult<()>{
-    // Consumer will process each search task one by one 
-    while let Some(task) = rx.recv().await{
-        let course = task.course;
-        let term_id = task.term;
-        
-        //Open a new tab for each search task 
-        let new_tab_handle = {
-            let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
-            driver_lock.new_tab().await?;
-            driver_lock.windows().await?.into_iter().last().clone().unwrap()
-        };
-        // Navigate to Class Search Tab
-        {
-            let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
-            driver_lock.switch_to_window(new_tab_handle.clone()).await?;
-            if driver_lock.title().await? != "Class Search and Enroll" {
-                driver_lock
-                    .goto(CUSIS_COURSE_SEARCH_LINK)
-                    .await?;
-            }
+// the buttons between "Review Class Selection" and the submit result, clicked in this order
+pub const ENROLL_BUTTONS: [&str; 5] = ["Next", "Accept", "Next", "Submit", "Yes"];
+
+// Looks up one course in the tab `new_tab_handle`, storing its options or, when enrolling, adding the requested classes
+// to the cart. A dry run returns what the review page would have submitted instead.
+async fn search_in_tab(
+    task: CourseSearchTask,
+    new_tab_handle: WindowHandle,
+    driver: &Arc<Mutex<WebDriver>>,
+    course_collection: &mut Arc<DashMap<String, Vec<Course>>>,
+    mode: TaskMode
+) -> CusisResult<Option<ClassReview>> {
+    let course = task.course;
+    let term_id = task.term;
+    // Navigate to Class Search Tab
+    {
+        let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
+        driver_lock.switch_to_window(new_tab_handle.clone()).await?;
+        if driver_lock.title().await? != "Class Search and Enroll" {
+            driver_lock
+                .goto(config::cusis_course_search_link())
+                .await?;
         }
-        async_wait_til_title(&driver, "Class Search and Enroll").await?;
-        // Select the correct term for search 
-        {
-            let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
-            if let Err(_) = driver_lock.find(By::Id("DERIVED_SSR_FL_SSR_CHANGE_BTN")).await{
-                let term_dropdown_button = driver_lock.find(By::Id("DERIVED_SSR_FL_SSR_CSTRMPRV_GRP")).await?;
-                println!("{:?}", term_dropdown_button);
-                term_dropdown_button.click().await?;
+    }
+    async_wait_til_title(driver, "Class Search and Enroll").await?;
+    // Select the correct term for search 
+    {
+        let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
+        if driver_lock.find(By::Id("DERIVED_SSR_FL_SSR_CHANGE_BTN")).await.is_err(){
+            let term_dropdown_button = driver_lock.find(By::Id("DERIVED_SSR_FL_SSR_CSTRMPRV_GRP")).await?;
+            term_dropdown_button.click().await?;
 
+            term_dropdown_button.click().await?;
+            match driver_lock.find(By::XPath(format!("//a[text()='{}']", term_id))).await{
+                Ok(element) => {
+                    element.click().await?
+                },
+                Err(_) => {
                 term_dropdown_button.click().await?;
-                match driver_lock.find(By::XPath(format!("//a[text()='{}']", term_id))).await{
-                    Ok(element) => {
-                        element.click().await?
-                    },
-                    Err(e) => {
-                    term_dropdown_button.click().await?;
-                        if let Ok(element) = driver_lock.find(By::XPath(format!("//a[text()='{}']", term_id))).await{
-                            element.click().await?;
-                        }
-                        else{
-                            println!("Failed to click term button");
-                        }
+                    if let Ok(element) = driver_lock.find(By::XPath(format!("//a[text()='{}']", term_id))).await{
+                        element.click().await?;
                     }
-                };
-            }
-            
-        }
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-        // Conduct search by filling in the search bar with the course code
-        {
-            let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
-            let fill_search_bar_with_course_js = format!("document.getElementById('PTS_KEYWORDS3').value = '{}';", course.clone());
-            driver_lock
-            .execute(&fill_search_bar_with_course_js, vec![])
-            .await?;
-            let title = driver_lock.title().await?;
-            search_and_click_element_with_retries(&driver_lock, By::Id("PTS_SRCH_BTN"), 5, &Option::Some(title)).await?;
-        }
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-        async_wait_til_title(&driver, "Class Search Results").await?;
-        {
-            let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
-            let _ = match driver_lock.find(By::Css("#PTS_LIST_TITLE\\$0")).await{
-                Ok(element)=> {
-                    println!("{element}");
-                    match element.attr("href").await {
-                        Ok(Some(link)) => {
-                            let owned_link = link.to_owned();
-                            driver_lock.goto(&owned_link).await?;
-                            tokio::time::sleep(tokio::time::Duration::from_micros(500)).await;
-                            while driver_lock.title().await? != "Course Information" {
-                                driver_lock.goto(&owned_link).await?;
-                                tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-                            }
-                        },
-                        Ok(None) => println!("{} cannot be found!", course),
-                        Err(e) => return Err(e),
+                    else{
+                        println!("Failed to click term button");
                     }
-                },
-                Err(e)=> {
-                    println!("{course} cannot be found in this semester, please make sure the course you are searching are open in this semester! ");
-                    return Err(e);
                 }
             };
         }
-        async_wait_til_title(&driver, "Course Information").await?;
-        if !enroll{
-            let rows = {
-                let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
-                driver_lock.find(By::ClassName("ps_grid-body")).await
-            };
-            save_course_data(rows,  course, &mut course_collection).await?;
-        }
-        else{
-            let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
-            if let Some(codes_to_be_searched) = task.code{
-                let text;
-                if codes_to_be_searched.len() > 1 {
-                    text = format!("//*[text()='{}']", codes_to_be_searched[1]);
-                }
-                else{
-                    text = format!("//*[text()='{}']", codes_to_be_searched[0]);
-                }
-                match driver_lock.find(By::XPath(text)).await{
-                    Ok(mut element) => {
-                        loop {
-                            let parent = element.parent()
-                            .await?;
-                            if parent.tag_name().await? == "tr"{
-                                break
-                            }
-                            element = parent;
-                        }
-                        if element.is_clickable().await?{
-                            element.click().await?;
-                            wait_til_title(&driver_lock, "Review Class Selection").await?;
-                            search_and_click_element_with_retries(&driver_lock, By::XPath("//*[text()='Next']"), 10, &Option::None).await?;
-                            search_and_click_element_with_retries(&driver_lock, By::XPath("//*[text()='Accept']"), 10, &Option::None).await?;
-                            search_and_click_element_with_retries(&driver_lock, By::XPath("//*[text()='Next']"), 10, &Option::None).await?;
-                            search_and_click_element_with_retries(&driver_lock, By::XPath("//*[text()='Submit']"), 10, &Option::None).await?;
-                            search_and_click_element_with_retries(&driver_lock, By::XPath("//*[text()='Yes']"), 10, &Option::None).await?;
-                            // tokio::time::sleep(tokio::time::Duration::from_micros(500)).await;
-                            // driver_lock.find(By::XPath("//*[text()='Submit']")).await?.click().await?;
-                            // tokio::time::sleep(tokio::time::Duration::from_micros(500)).await;
-                            // driver_lock.find(By::XPath("//*[text()='Yes']")).await?.click().await?;
-                            let title = driver_lock.title().await?;
-                            search_and_click_element_with_retries(&driver_lock, By::XPath("//*[text()='Return to Keyword Search Page']"), 10, &Option::Some(title)).await?;
-                            println!("{} has been added to shopping cart!", course);
-                        }
-                    },
-                    Err(e) => return Err(e)
+        
+    }
+    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    // Conduct search by filling in the search bar with the course code
+    {
+        let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
+        let fill_search_bar_with_course_js = format!("document.getElementById('PTS_KEYWORDS3').value = '{}';", course.clone());
+        driver_lock
+        .execute(&fill_search_bar_with_course_js, vec![])
+        .await?;
+        let title = driver_lock.title().await?;
+        search_and_click_element_with_retries(&driver_lock, By::Id("PTS_SRCH_BTN"), 5, &Option::Some(title)).await?;
+    }
+    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    async_wait_til_title(driver, "Class Search Results").await?;
+    {
+        let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
+        let results = parse_search_results(&driver_lock.source().await?);
+        match results.first(){
+            Some(SearchResult { link: Some(link), .. }) => {
+                // hrefs may be relative to the results page
+                let link = match driver_lock.current_url().await?.join(link) {
+                    Ok(url) => url.to_string(),
+                    Err(_) => link.clone()
+                };
+                driver_lock.goto(&link).await?;
+                tokio::time::sleep(tokio::time::Duration::from_micros(500)).await;
+                while driver_lock.title().await? != "Course Information" {
+                    driver_lock.goto(&link).await?;
+                    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
                 }
+            },
+            // listed without a link to its "Course Information" page, there is nothing to open
+            Some(SearchResult { link: None, .. }) => {
+                println!("{} cannot be found!", course);
+                return Err(CusisError::CourseNotOffered(course));
+            },
+            None => {
+                println!("{course} cannot be found in this semester, please make sure the course you are searching are open in this semester! ");
+                return Err(CusisError::CourseNotOffered(course));
             }
-        }
-        {
+        };
+    }
+    async_wait_til_title(driver, "Course Information").await?;
+    if mode == TaskMode::Search {
+        let page_source = {
             let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
-            driver_lock.switch_to_window(new_tab_handle.clone()).await?;
-            driver_lock.close_window().await?;
-            let handles = driver_lock.windows().await?;
-            if let Some(first_handle) = handles.first() {
-                driver_lock.switch_to_window(first_handle.clone()).await?;
+            driver_lock.source().await
+        };
+        save_course_data(page_source, course, course_collection).await?;
+    }
+    else{
+        let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
+        if let Some(codes) = task.code{
+            // codes are the class numbers of the whole combination, the primary section first
+            let wanted: Vec<u32> = codes.iter()
+                .map(|code| code.trim().parse::<u32>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| CusisError::InvalidCourseData(format!("class numbers {:?} of {}", codes, course)))?;
+            let rows = find_class_rows(&driver_lock.source().await?, &wanted);
+            let [row_index] = rows[..] else {
+                // no row, or several rows that cannot be told apart, would mean guessing
+                return Err(CusisError::PageLayoutChanged(format!("exactly one class row of {} with classes {:?}, found {}", course, wanted, rows.len())));
+            };
+            let row = driver_lock.find_all(By::Css(CLASS_ROW_SELECTOR)).await?
+                .into_iter()
+                .nth(row_index)
+                .ok_or_else(|| CusisError::PageLayoutChanged(format!("class row {} of {}", row_index + 1, course)))?;
+            let cell = row.find(By::Tag("td")).await?;
+            if !cell.is_clickable().await?{
+                return Err(CusisError::PageLayoutChanged(format!("a selectable class row of {}", course)));
+            }
+            cell.click().await?;
+            wait_til_title(&driver_lock, "Review Class Selection").await?;
+            if mode == TaskMode::DryRun {
+                // the class only reaches the cart once Submit is clicked, leaving here leaves the cart as it was
+                let review = ClassReview {
+                    classes: parse_class_review(&driver_lock.source().await?),
+                    screenshot: screenshot::capture(&driver_lock, &format!("review-{}", course)).await
+                };
+                println!("{} would be submitted as {:?}", course, review.classes);
+                return Ok(Some(review));
             }
+            for button in ENROLL_BUTTONS {
+                search_and_click_element_with_retries(&driver_lock, By::XPath(format!("//*[text()='{}']", button)), 10, &Option::None).await?;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+            // CUSIS may accept a neighbouring row just as happily, so the result has to name the classes picked
+            let result = parse_submit_result(&driver_lock.source().await?);
+            if !result.succeeded {
+                return Err(CusisError::EnrollmentRejected(format!("{} ({})", result.message, course)));
+            }
+            if let Some(missing) = wanted.iter().find(|number| !result.class_numbers.contains(number)) {
+                return Err(CusisError::EnrollmentRejected(format!("the result of {} lists {:?} but not class {}", course, result.class_numbers, missing)));
+            }
+            let title = driver_lock.title().await?;
+            search_and_click_element_with_retries(&driver_lock, By::XPath("//*[text()='Return to Keyword Search Page']"), 10, &Option::Some(title)).await?;
+            println!("{} has been added to shopping cart!", course);
+        }
+    }
+    Ok(None)
+}
+
+// Opens a tab for `task`, looks the course up there and closes the tab again whatever the outcome. A failure is
+// captured before the tab closes, while it still shows the page the lookup stopped on
+async fn search_in_new_tab(
+    task: CourseSearchTask,
+    driver: &Arc<Mutex<WebDriver>>,
+    course_collection: &mut Arc<DashMap<String, Vec<Course>>>,
+    mode: TaskMode
+) -> CusisResult<Option<ClassReview>> {
+    //Open a new tab for each search task 
+    let new_tab_handle = {
+        let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
+        driver_lock.new_tab().await?;
+        driver_lock.windows().await?.into_iter().last().clone().unwrap()
+    };
+    let step = match mode {
+        TaskMode::Search => "search",
+        TaskMode::Enroll => "submit",
+        TaskMode::DryRun => "review"
+    };
+    let context = format!("{}-{}", step, task.course);
+    let result = search_in_tab(task, new_tab_handle.clone(), driver, course_collection, mode).await;
+    let result = screenshot::on_error(&*driver.lock().await, &context, result).await;
+    {
+        let driver_lock: tokio::sync::MutexGuard<'_, WebDriver> = driver.lock().await;
+        driver_lock.switch_to_window(new_tab_handle.clone()).await?;
+        driver_lock.close_window().await?;
+        let handles = driver_lock.windows().await?;
+        if let Some(first_handle) = handles.first() {
+            driver_lock.switch_to_window(first_handle.clone()).await?;
+        }
+    }
+    result
+}
+
+// Consumes the search tasks one by one, `outcomes` receives how each course went when the caller reports them
+pub async fn process_search_tasks(
+    mut rx: tokio::sync::mpsc::Receiver<CourseSearchTask>,
+    driver: Arc<Mutex<WebDriver>>,
+    mut course_collection: Arc<DashMap<String, Vec<Course>>>,
+    mode: TaskMode,
+    session: Arc<Session>,
+    outcomes: Option<UnboundedSender<(String, CusisResult<Option<ClassReview>>)>>
+)-> CusisResult<()>{
+    // Consumer will process each search task one by one 
+    while let Some(task) = rx.recv().await{
+        let course = task.course.clone();
+        let mut result = search_in_new_tab(task.clone(), &driver, &mut course_collection, mode).await;
+        if let Err(CusisError::SessionExpired) = result {
+            // the queue waits here, holding the driver, until the user is signed back in
+            println!("The CUSIS session expired while looking up {}, signing in again", task.course);
+            {
+                let driver_lock = driver.lock().await;
+                screenshot::on_error(&driver_lock, "restore-session", session.restore(&driver_lock).await).await?;
+            }
+            result = search_in_new_tab(task, &driver, &mut course_collection, mode).await;
+        }
+        let outcome = match result {
+            Ok(review) => Ok(review),
+            // one course missing from the term should not stop the other courses from being looked up
+            Err(e @ (CusisError::CourseNotOffered(_) | CusisError::NoOpenSections(_))) => {
+                eprintln!("{}", e);
+                Err(e)
+            },
+            // nor should one class that cannot be added stop the rest of the schedule from reaching the cart
+            Err(e) if mode != TaskMode::Search => {
+                eprintln!("Failed to add {} to the shopping cart: {}", course, e);
+                Err(e)
+            },
+            Err(e) => return Err(e)
+        };
+        if let Some(outcomes) = &outcomes {
+            let _ = outcomes.send((course, outcome));
         }
-        // if let Some(spinner) = spinner_option{
-        //     spinner.stop();
-        // }
-        
     }
     Ok(())
-}
\ No newline at end of file
+}
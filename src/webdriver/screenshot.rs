@@ -0,0 +1,47 @@
+use std::fs;
+use std::path::PathBuf;
+use chrono::Local;
+use thirtyfour::prelude::*;
+use crate::config;
+use crate::parser::cusis_page::anonymize_page;
+
+// Saves what the browser is showing as <screenshot dir>/<timestamp>-<context>.png, which is the only way to see
+// what CUSIS displayed when a headless session fails or what a dry run would have submitted. The page source is kept
+// beside it as .html with the student's details blanked out, ready to become a parser fixture
+pub async fn capture(driver: &WebDriver, context: &str) -> Option<PathBuf> {
+    let dir = config::screenshot_dir();
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Cannot create screenshot directory {}: {}", dir.display(), e);
+        return None;
+    }
+    let context: String = context.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let path = dir.join(format!("{}-{}.png", Local::now().format("%Y%m%d-%H%M%S"), context));
+    match driver.source().await {
+        Ok(html) => {
+            if let Err(e) = fs::write(path.with_extension("html"), anonymize_page(&html)) {
+                eprintln!("Failed to save the page source: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to read the page source: {}", e)
+    }
+    match driver.screenshot(&path).await {
+        Ok(()) => {
+            eprintln!("Saved a screenshot to {}", path.display());
+            Some(path)
+        }
+        Err(e) => {
+            eprintln!("Failed to take a screenshot: {}", e);
+            None
+        }
+    }
+}
+
+// passes `result` through, capturing the page first when it is an error
+pub async fn on_error<T, E>(driver: &WebDriver, context: &str, result: Result<T, E>) -> Result<T, E> {
+    if result.is_err() {
+        capture(driver, context).await;
+    }
+    result
+}
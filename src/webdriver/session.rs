@@ -0,0 +1,177 @@
+use std::sync::Mutex;
+use thirtyfour::prelude::*;
+use thirtyfour::Cookie;
+use tokio::sync::oneshot;
+use tokio::time::{self, Duration};
+use crate::config;
+use crate::error::{CusisError, CusisResult};
+use super::cookie_store::CookieStore;
+use super::duo;
+use super::util::{goto_cusis, handles_auth, login};
+
+// texts PeopleSoft shows in place of the requested page once a session has timed out
+const TIMEOUT_NOTICES: [&str; 3] = ["session has timed out", "session has expired", "you have been signed out"];
+// how long a restore waits for the user to pick a Duo option on the 2FA page
+const DUO_CHOICE_TIMEOUT: Duration = Duration::from_secs(120);
+
+// What the user is told while a session is being restored
+pub enum SessionEvent {
+    Expired,
+    // the labels of the Duo options to choose from, in the order choose_duo() takes them
+    DuoChoice(Vec<String>),
+    DuoStatus(String),
+    Restored
+}
+
+// Keeps what is needed to sign back in once PeopleSoft times the session out: the cookies of the last good session
+// and the credentials typed on the login page. The password only ever lives in memory, for as long as the app runs,
+// the cookies are also written to `store` so the next run can skip the login.
+pub struct Session {
+    credentials: Mutex<Option<(String, String)>>,
+    cookies: Mutex<Vec<Cookie>>,
+    store: Option<CookieStore>,
+    notify: Box<dyn Fn(SessionEvent) + Send + Sync>,
+    // where the option picked on the 2FA page goes while a restore waits for one
+    duo_choice: Mutex<Option<oneshot::Sender<(usize, String)>>>
+}
+
+// true when CUSIS bounced the browser to the login page or replaced the page with a timeout notice
+pub async fn is_session_expired(driver: &WebDriver) -> bool {
+    if driver.title().await.is_ok_and(|title| title == "Sign In") {
+        return true;
+    }
+    match driver.source().await {
+        Ok(source) => {
+            let source = source.to_lowercase();
+            TIMEOUT_NOTICES.iter().any(|notice| source.contains(notice))
+        }
+        Err(_) => false
+    }
+}
+
+impl Session {
+    pub fn new(store: Option<CookieStore>, notify: impl Fn(SessionEvent) + Send + Sync + 'static) -> Session {
+        Session {
+            credentials: Mutex::new(None),
+            cookies: Mutex::new(vec![]),
+            store,
+            notify: Box::new(notify),
+            duo_choice: Mutex::new(None)
+        }
+    }
+
+    // Hands the Duo option picked on the 2FA page to the restore waiting for one, false when no restore is waiting
+    pub fn choose_duo(&self, index: usize, passcode: String) -> bool {
+        match self.duo_choice.lock().unwrap().take() {
+            Some(sender) => sender.send((index, passcode)).is_ok(),
+            None => false
+        }
+    }
+
+    pub fn has_saved_cookies(&self) -> bool {
+        self.store.as_ref().is_some_and(|store| store.exists())
+    }
+
+    pub fn remember_credentials(&self, username: String, password: String) {
+        *self.credentials.lock().unwrap() = Some((username, password));
+    }
+
+    // call once signed in, the cookies are tried first the next time the session expires
+    pub async fn save_cookies(&self, driver: &WebDriver) -> CusisResult<()> {
+        let cookies = driver.get_all_cookies().await?;
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(&cookies) {
+                eprintln!("Failed to keep the session for the next run: {}", e);
+            }
+        }
+        *self.cookies.lock().unwrap() = cookies;
+        Ok(())
+    }
+
+    // Signs in with the cookies the last run stored, true when CUSIS still accepts them and the login can be skipped
+    pub async fn resume(&self, driver: &WebDriver) -> CusisResult<bool> {
+        let Some(cookies) = self.store.as_ref().and_then(|store| store.load()) else {
+            return Ok(false);
+        };
+        *self.cookies.lock().unwrap() = cookies;
+        if self.restore_cookies(driver).await? {
+            return Ok(true);
+        }
+        println!("The saved CUSIS session has expired, please log in again");
+        if let Some(store) = &self.store {
+            store.clear();
+        }
+        self.cookies.lock().unwrap().clear();
+        goto_cusis(driver).await?;
+        Ok(false)
+    }
+
+    // loads the saved cookies into the browser, true when CUSIS takes them as a signed in session. They still
+    // hold up when only the browser lost its session or the app restarted, not once CUSIS ended the session itself
+    async fn restore_cookies(&self, driver: &WebDriver) -> CusisResult<bool> {
+        let cookies = self.cookies.lock().unwrap().clone();
+        if cookies.is_empty() {
+            return Ok(false);
+        }
+        driver.goto(config::cusis_course_search_link()).await?;
+        driver.delete_all_cookies().await?;
+        for cookie in cookies {
+            // a cookie of another domain is refused, the rest are still worth trying
+            let _ = driver.add_cookie(cookie).await;
+        }
+        // the class search page only loads for a signed in session
+        driver.goto(config::cusis_course_search_link()).await?;
+        time::sleep(Duration::from_secs(1)).await;
+        Ok(!is_session_expired(driver).await)
+    }
+
+    // Signs back in on the current tab, with the saved cookies when they still work and otherwise with the
+    // remembered credentials and a new Duo approval
+    pub async fn restore(&self, driver: &WebDriver) -> CusisResult<()> {
+        (self.notify)(SessionEvent::Expired);
+        if self.restore_cookies(driver).await? {
+            (self.notify)(SessionEvent::Restored);
+            return Ok(());
+        }
+        let (username, password) = self.credentials.lock().unwrap().clone().ok_or(CusisError::SessionExpired)?;
+        goto_cusis(driver).await?;
+        login(driver, username, password).await?;
+        // Duo sends its default push by itself when it offers no choice, otherwise the user picks on the 2FA page
+        let options = duo::list_options(driver).await?;
+        if !options.is_empty() {
+            let (sender, receiver) = oneshot::channel();
+            *self.duo_choice.lock().unwrap() = Some(sender);
+            (self.notify)(SessionEvent::DuoChoice(options.into_iter().map(|option| option.label).collect()));
+            let choice = time::timeout(DUO_CHOICE_TIMEOUT, receiver).await;
+            self.duo_choice.lock().unwrap().take();
+            let Ok(Ok((index, passcode))) = choice else {
+                return Err(CusisError::DuoTimeout);
+            };
+            let method = duo::request(driver, index, &passcode).await?;
+            (self.notify)(SessionEvent::DuoStatus(duo::waiting_status(method).to_string()));
+        }
+        handles_auth(driver, |status| (self.notify)(SessionEvent::DuoStatus(status))).await?;
+        // the same pause the login page takes before it moves on to the terms
+        time::sleep(Duration::from_secs(5)).await;
+        self.save_cookies(driver).await?;
+        (self.notify)(SessionEvent::Restored);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duo_choice_only_reaches_a_waiting_restore() {
+        let session = Session::new(None, |_| {});
+        assert!(!session.choose_duo(0, String::new()));
+        let (sender, mut receiver) = oneshot::channel();
+        *session.duo_choice.lock().unwrap() = Some(sender);
+        assert!(session.choose_duo(2, "123456".to_string()));
+        assert_eq!(receiver.try_recv().unwrap(), (2, "123456".to_string()));
+        // the choice is used up
+        assert!(!session.choose_duo(0, String::new()));
+    }
+}